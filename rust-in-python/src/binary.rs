@@ -0,0 +1,555 @@
+//! Input of work as CBOR or MessagePack, which are more compact than JSON
+//! for large arrays of numbers. Work in these formats is laid out just like
+//! it is in JSON, with objects encoded as maps keyed by strings.
+
+use futures::{AsyncRead, AsyncReadExt};
+use serde::de::DeserializeOwned;
+
+use crate::{
+    error::{Location, PathPiece, StrompyError},
+    scope::Scope,
+    validate::{ReadValue, Validator},
+    InputMode, ItemError, PieceOfWork, RawPieceOfWork, StrompyResult, WorkReaderState, WorkResult,
+};
+
+/// The number of bytes [BinaryWorkReader] tries to read at once
+const CHUNK_SIZE: usize = 8 * 1024;
+
+/// The CBOR marker that terminates an item of indefinite length
+const CBOR_BREAK: u8 = 0xff;
+
+/// A binary encoding of work
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BinaryFormat {
+    /// Concise Binary Object Representation, RFC 8949
+    Cbor,
+    /// MessagePack, see <https://msgpack.org>
+    MessagePack,
+}
+
+impl std::str::FromStr for BinaryFormat {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "cbor" => Ok(Self::Cbor),
+            "msgpack" => Ok(Self::MessagePack),
+            _ => Err(format!(
+                r#"Unknown format "{s}", expected "json", "cbor" or "msgpack""#
+            )),
+        }
+    }
+}
+
+/// What follows the header of an encoded item
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Items {
+    /// A fixed number of nested items
+    Count(usize),
+    /// Nested items up until a break marker
+    Indefinite,
+    /// Nothing, the item is a break marker itself
+    Break,
+}
+
+impl BinaryFormat {
+    /// Deserialize a value from `bytes`, which start at `offset` in the input
+    pub fn decode<T: DeserializeOwned>(self, bytes: &[u8], offset: u64) -> StrompyResult<T> {
+        match self {
+            BinaryFormat::Cbor => ciborium::from_reader(bytes).map_err(|e| {
+                let e_offset = match &e {
+                    ciborium::de::Error::Syntax(o) | ciborium::de::Error::Semantic(Some(o), _) => {
+                        Some(offset + *o as u64)
+                    }
+                    _ => None,
+                };
+                let e = StrompyError::Cbor(e);
+                match e_offset {
+                    Some(offset) => e.at(Location {
+                        offset: Some(offset),
+                        ..Default::default()
+                    }),
+                    None => e,
+                }
+            }),
+            BinaryFormat::MessagePack => {
+                let mut deserializer = rmp_serde::Deserializer::new(bytes);
+                serde_path_to_error::deserialize(&mut deserializer).map_err(|e| {
+                    let location = Location::of_path(e.path());
+                    StrompyError::MessagePack(e.into_inner()).at(location)
+                })
+            }
+        }
+    }
+
+    /// An error describing malformed input at `offset`
+    fn syntax_error(self, offset: u64, msg: &str) -> StrompyError {
+        let e = match self {
+            BinaryFormat::Cbor => StrompyError::Cbor(ciborium::de::Error::Syntax(offset as usize)),
+            BinaryFormat::MessagePack => {
+                StrompyError::MessagePack(rmp_serde::decode::Error::Uncategorized(msg.to_owned()))
+            }
+        };
+        e.at(Location {
+            offset: Some(offset),
+            ..Default::default()
+        })
+    }
+
+    /// An error describing input that ends in the middle of an item
+    fn unexpected_eof(self, offset: u64) -> StrompyError {
+        let io_error = std::io::Error::from(std::io::ErrorKind::UnexpectedEof);
+        let e = match self {
+            BinaryFormat::Cbor => StrompyError::Cbor(ciborium::de::Error::Io(io_error)),
+            BinaryFormat::MessagePack => {
+                StrompyError::MessagePack(rmp_serde::decode::Error::InvalidDataRead(io_error))
+            }
+        };
+        e.at(Location {
+            offset: Some(offset),
+            ..Default::default()
+        })
+    }
+
+    /// Whether `marker` starts an array
+    fn is_array(self, marker: u8) -> bool {
+        match self {
+            BinaryFormat::Cbor => marker >> 5 == 4,
+            BinaryFormat::MessagePack => matches!(marker, 0x90..=0x9f | 0xdc | 0xdd),
+        }
+    }
+
+    /// Parse the header of the item at the start of `buf`, which starts at `offset`
+    /// in the input. Returns the number of bytes taken up by the header and any
+    /// payload that is not an item by itself, along with what follows it.
+    /// Returns `None` if `buf` does not hold all of these bytes yet.
+    fn header(self, buf: &[u8], offset: u64) -> StrompyResult<Option<(usize, Items)>> {
+        let Some(&marker) = buf.first() else {
+            return Ok(None);
+        };
+
+        // Read a big-endian unsigned integer of `size` bytes following the marker
+        let arg = |size: usize| -> StrompyResult<Option<usize>> {
+            let Some(bytes) = buf.get(1..1 + size) else {
+                return Ok(None);
+            };
+            let arg = bytes.iter().fold(0u64, |arg, &b| arg << 8 | b as u64);
+            usize::try_from(arg)
+                .map(Some)
+                .map_err(|_| self.syntax_error(offset, "length out of range"))
+        };
+        // The length of a header of `len` bytes followed by a payload of `payload` bytes
+        let with_payload = |len: usize, payload: usize| -> StrompyResult<usize> {
+            len.checked_add(payload)
+                .ok_or_else(|| self.syntax_error(offset, "length out of range"))
+        };
+
+        let header = match self {
+            BinaryFormat::Cbor => {
+                if marker == CBOR_BREAK {
+                    return Ok(Some((1, Items::Break)));
+                }
+
+                let (major, info) = (marker >> 5, marker & 0x1f);
+                let size = match info {
+                    0..=23 => 0,
+                    24..=27 => 1 << (info - 24),
+                    31 if matches!(major, 2..=5) => return Ok(Some((1, Items::Indefinite))),
+                    _ => return Err(self.syntax_error(offset, "invalid additional information")),
+                };
+                let arg = match size {
+                    0 => Some(info as usize),
+                    size => arg(size)?,
+                };
+
+                arg.map(|arg| {
+                    Ok(match major {
+                        // Byte and text strings
+                        2 | 3 => (with_payload(1 + size, arg)?, Items::Count(0)),
+                        // Arrays
+                        4 => (1 + size, Items::Count(arg)),
+                        // Maps
+                        5 => (1 + size, Items::Count(arg.saturating_mul(2))),
+                        // Tags, which apply to the item that follows
+                        6 => (1 + size, Items::Count(1)),
+                        // Integers, floats and simple values
+                        _ => (1 + size, Items::Count(0)),
+                    })
+                })
+                .transpose()?
+            }
+            BinaryFormat::MessagePack => {
+                type Header = StrompyResult<Option<(usize, Items)>>;
+
+                let fixed = |len: usize| -> Header { Ok(Some((len, Items::Count(0)))) };
+                // A bin, str or ext item, of which the length takes `size` bytes,
+                // followed by `extra` bytes before the payload
+                let sized = |size: usize, extra: usize| -> Header {
+                    arg(size)?
+                        .map(|len| Ok((with_payload(1 + size + extra, len)?, Items::Count(0))))
+                        .transpose()
+                };
+                // An array or map, of which the number of entries takes `size` bytes
+                let counted = |size: usize, items_per_entry: usize| -> Header {
+                    Ok(arg(size)?.map(|count| {
+                        (1 + size, Items::Count(count.saturating_mul(items_per_entry)))
+                    }))
+                };
+
+                let header = match marker {
+                    // Nil, booleans and fixed-size integers
+                    0x00..=0x7f | 0xc0 | 0xc2 | 0xc3 | 0xe0..=0xff => fixed(1),
+                    // fixmap, fixarray and fixstr
+                    0x80..=0x8f => Ok(Some((1, Items::Count(2 * (marker & 0x0f) as usize)))),
+                    0x90..=0x9f => Ok(Some((1, Items::Count((marker & 0x0f) as usize)))),
+                    0xa0..=0xbf => fixed(1 + (marker & 0x1f) as usize),
+                    // bin 8, 16 and 32
+                    0xc4..=0xc6 => sized(1 << (marker - 0xc4), 0),
+                    // ext 8, 16 and 32, with a type byte before the payload
+                    0xc7..=0xc9 => sized(1 << (marker - 0xc7), 1),
+                    // float 32 and 64
+                    0xca => fixed(5),
+                    0xcb => fixed(9),
+                    // uint and int 8, 16, 32 and 64
+                    0xcc..=0xcf => fixed(1 + (1 << (marker - 0xcc))),
+                    0xd0..=0xd3 => fixed(1 + (1 << (marker - 0xd0))),
+                    // fixext 1, 2, 4, 8 and 16, with a type byte before the payload
+                    0xd4..=0xd8 => fixed(2 + (1 << (marker - 0xd4))),
+                    // str 8, 16 and 32
+                    0xd9..=0xdb => sized(1 << (marker - 0xd9), 0),
+                    // array 16 and 32
+                    0xdc | 0xdd => counted(2 << (marker - 0xdc), 1),
+                    // map 16 and 32
+                    0xde | 0xdf => counted(2 << (marker - 0xde), 2),
+                    0xc1 => Err(self.syntax_error(offset, "reserved marker 0xc1")),
+                };
+                header?
+            }
+        };
+
+        let Some((len, items)) = header else {
+            return Ok(None);
+        };
+        if buf.len() < len {
+            return Ok(None);
+        }
+        Ok(Some((len, items)))
+    }
+}
+
+/// Execute an array of [PieceOfWork]s encoded in `format`
+/// that has already been read fully into memory.
+pub fn exec_binary(bytes: &[u8], format: BinaryFormat) -> StrompyResult<Vec<WorkResult>> {
+    let work: Vec<RawPieceOfWork> = format.decode(bytes, 0)?;
+    crate::exec_all(crate::from_raw(work)?)
+}
+
+/// Progress in finding the end of the next item in the buffer
+#[derive(Debug)]
+struct Scan {
+    /// The offset up to which the item has been scanned
+    pos: usize,
+    /// For every item that is being scanned, the number of nested items
+    /// left, or `None` if it is terminated by a break marker
+    stack: Vec<Option<usize>>,
+}
+
+impl Default for Scan {
+    fn default() -> Self {
+        Self {
+            pos: 0,
+            stack: vec![Some(1)],
+        }
+    }
+}
+
+/// Reads [PieceOfWork]s encoded in a [BinaryFormat] and laid out according
+/// to an [InputMode], executing them one by one. Only a single piece of work
+/// is buffered at any time.
+pub struct BinaryWorkReader<R> {
+    reader: R,
+    format: BinaryFormat,
+    mode: InputMode,
+    state: WorkReaderState,
+    /// Bytes that were read, but not decoded yet
+    buf: Vec<u8>,
+    /// The offset in the input of the first byte in `buf`
+    offset: u64,
+    scan: Scan,
+    /// In [InputMode::Array], the number of items left in the array,
+    /// or `None` if the array is terminated by a break marker
+    remaining: Option<usize>,
+    /// The index of the next item
+    index: usize,
+    /// Named results of the work read so far
+    scope: Scope<'static>,
+}
+
+impl<R: AsyncRead + Unpin> BinaryWorkReader<R> {
+    pub fn new(reader: R, format: BinaryFormat, mode: InputMode) -> Self {
+        Self {
+            reader,
+            format,
+            mode,
+            state: WorkReaderState::Start,
+            buf: Vec::new(),
+            offset: 0,
+            scan: Scan::default(),
+            remaining: None,
+            index: 0,
+            scope: Scope::new(),
+        }
+    }
+
+    /// Read and execute the next [PieceOfWork], returning `None` once
+    /// the end of the input is reached. Once an error is returned,
+    /// every subsequent call will return an error as well.
+    pub async fn next(&mut self) -> StrompyResult<Option<WorkResult>> {
+        match self.state {
+            WorkReaderState::Done => return Ok(None),
+            WorkReaderState::Failed => return Err(StrompyError::Poisoned),
+            WorkReaderState::Start | WorkReaderState::Reading => {}
+        }
+
+        let res = self.read_next().await.and_then(Option::transpose);
+        if res.is_err() {
+            self.state = WorkReaderState::Failed;
+        }
+        res
+    }
+
+    /// Like [BinaryWorkReader::next], but an error in a single [PieceOfWork]
+    /// does not end reading. Instead, it is returned as an [ItemError]. Only
+    /// errors in the encoding of the input, after which the next item cannot
+    /// be found, are returned as an error, which every subsequent call will
+    /// return as well.
+    pub async fn next_item(&mut self) -> StrompyResult<Option<Result<WorkResult, ItemError>>> {
+        match self.state {
+            WorkReaderState::Done => return Ok(None),
+            WorkReaderState::Failed => return Err(StrompyError::Poisoned),
+            WorkReaderState::Start | WorkReaderState::Reading => {}
+        }
+
+        let index = self.index;
+        match self.read_next().await {
+            Ok(res) => Ok(res.map(|res| res.map_err(|error| ItemError { index, error }))),
+            Err(e) => {
+                self.state = WorkReaderState::Failed;
+                Err(e)
+            }
+        }
+    }
+
+    /// Check all remaining work without executing it, returning every problem
+    /// found. Ends at the first error in the encoding of the input, which is
+    /// returned as the last problem.
+    pub async fn validate(mut self) -> Vec<StrompyError> {
+        let mut validator = Validator::new();
+        match self.state {
+            WorkReaderState::Done => return Vec::new(),
+            WorkReaderState::Failed => return vec![StrompyError::Poisoned],
+            WorkReaderState::Start | WorkReaderState::Reading => {}
+        }
+
+        loop {
+            let index = self.index;
+            match self.read_item::<ReadValue>().await {
+                Ok(Some(Ok(item))) => validator.piece_of_work(&item, &[PathPiece::Index(index)]),
+                Ok(Some(Err(e))) => validator.push(e.within(PathPiece::Index(index))),
+                Ok(None) => break,
+                Err(e) => {
+                    validator.push(e);
+                    break;
+                }
+            }
+        }
+        validator.into_problems()
+    }
+
+    /// Read and execute the next [PieceOfWork]. Errors in the piece of work
+    /// itself are returned as the inner error, after which the reader is
+    /// positioned at the next item. Any other error is returned as the outer error.
+    async fn read_next(&mut self) -> StrompyResult<Option<StrompyResult<WorkResult>>> {
+        let index = self.index;
+        let Some(work) = self.read_item::<RawPieceOfWork>().await? else {
+            return Ok(None);
+        };
+
+        // Results named within the piece of work are only kept if it succeeds
+        let res = work.and_then(PieceOfWork::try_from).and_then(|work| {
+            let mut scope = self.scope.child();
+            let res = work.exec_into(&mut scope)?;
+            let named = scope.into_results();
+            self.scope.extend(named);
+            Ok(res)
+        });
+        Ok(Some(res.map_err(|e| self.within_array(e, index))))
+    }
+
+    /// Read and decode the next item as a `T`. Errors in decoding the item itself
+    /// are returned as the inner error, after which the reader is positioned at
+    /// the next item. Any other error is returned as the outer error.
+    async fn read_item<T: DeserializeOwned>(&mut self) -> StrompyResult<Option<StrompyResult<T>>> {
+        if self.state == WorkReaderState::Start {
+            if self.mode == InputMode::Array {
+                self.remaining = self.read_array_header().await?;
+            }
+            self.state = WorkReaderState::Reading;
+        }
+
+        if !self.has_next().await? {
+            self.state = WorkReaderState::Done;
+            return Ok(None);
+        }
+
+        // Once the item is framed, it is consumed whether it can be decoded
+        // or not, so that reading can continue with the next item
+        let index = self.index;
+        let len = match self.frame().await {
+            Ok(len) => len,
+            Err(e) => return Err(self.within_array(e, index)),
+        };
+        let item = self.format.decode(&self.buf[..len], self.offset);
+        self.consume(len);
+        self.index += 1;
+        if let Some(remaining) = &mut self.remaining {
+            *remaining -= 1;
+        }
+
+        Ok(Some(item))
+    }
+
+    /// Prefix the path of an error in the item at `index` with
+    /// that index, if the items are held in an array
+    fn within_array(&self, e: StrompyError, index: usize) -> StrompyError {
+        match self.mode {
+            InputMode::Array => e.within(PathPiece::Index(index)),
+            InputMode::Values => e,
+        }
+    }
+
+    /// Read the header of the array that holds all work, returning the
+    /// number of items in it, or `None` if it is terminated by a break marker
+    async fn read_array_header(&mut self) -> StrompyResult<Option<usize>> {
+        loop {
+            if let Some((len, items)) = self.format.header(&self.buf, self.offset)? {
+                if !self.format.is_array(self.buf[0]) {
+                    return Err(self.format.syntax_error(self.offset, "expected an array"));
+                }
+                self.consume(len);
+                return Ok(match items {
+                    Items::Count(count) => Some(count),
+                    Items::Indefinite | Items::Break => None,
+                });
+            }
+
+            if !self.fill().await? {
+                return Err(self.format.unexpected_eof(self.offset));
+            }
+        }
+    }
+
+    /// Whether there is another item to read
+    async fn has_next(&mut self) -> StrompyResult<bool> {
+        match (self.mode, self.remaining) {
+            (InputMode::Array, Some(remaining)) => Ok(remaining > 0),
+            (InputMode::Array, None) => {
+                while self.buf.is_empty() {
+                    if !self.fill().await? {
+                        return Err(self.format.unexpected_eof(self.offset));
+                    }
+                }
+                if self.buf[0] == CBOR_BREAK {
+                    self.consume(1);
+                    Ok(false)
+                } else {
+                    Ok(true)
+                }
+            }
+            (InputMode::Values, _) => {
+                while self.buf.is_empty() {
+                    if !self.fill().await? {
+                        return Ok(false);
+                    }
+                }
+                Ok(true)
+            }
+        }
+    }
+
+    /// Read until the next item is fully buffered, returning its length in bytes
+    async fn frame(&mut self) -> StrompyResult<usize> {
+        loop {
+            if let Some(len) = self.scan()? {
+                self.scan = Scan::default();
+                return Ok(len);
+            }
+
+            if !self.fill().await? {
+                let end = self.offset + self.buf.len() as u64;
+                return Err(self.format.unexpected_eof(end));
+            }
+        }
+    }
+
+    /// Continue scanning the buffered bytes for the end of the next item,
+    /// returning its length once it is found
+    fn scan(&mut self) -> StrompyResult<Option<usize>> {
+        let Self {
+            format,
+            buf,
+            offset,
+            scan: Scan { pos, stack },
+            ..
+        } = self;
+
+        loop {
+            let Some(top) = stack.last_mut() else {
+                return Ok(Some(*pos));
+            };
+            if *top == Some(0) {
+                stack.pop();
+                continue;
+            }
+
+            let item_offset = *offset + *pos as u64;
+            let Some((len, items)) = format.header(&buf[*pos..], item_offset)? else {
+                return Ok(None);
+            };
+
+            match items {
+                Items::Break if top.is_none() => {
+                    stack.pop();
+                }
+                Items::Break => return Err(format.syntax_error(item_offset, "unexpected break")),
+                items => {
+                    if let Some(left) = top {
+                        *left -= 1;
+                    }
+                    match items {
+                        Items::Count(0) | Items::Break => {}
+                        Items::Count(count) => stack.push(Some(count)),
+                        Items::Indefinite => stack.push(None),
+                    }
+                }
+            }
+
+            *pos += len;
+        }
+    }
+
+    /// Drop the first `len` bytes from the buffer
+    fn consume(&mut self, len: usize) {
+        self.buf.drain(..len);
+        self.offset += len as u64;
+    }
+
+    /// Read more bytes into the buffer, returning `false` at the end of the input
+    async fn fill(&mut self) -> StrompyResult<bool> {
+        let len = self.buf.len();
+        self.buf.resize(len + CHUNK_SIZE, 0);
+        let read = self.reader.read(&mut self.buf[len..]).await;
+        self.buf.truncate(len + read.as_ref().map_or(0, |&read| read));
+        Ok(read? > 0)
+    }
+}
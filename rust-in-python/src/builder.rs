@@ -0,0 +1,247 @@
+//! Python-side API for building a [PieceOfWork] from NumPy arrays
+//! or earlier results, without serializing them to JSON first.
+
+use numpy::PyReadonlyArray2;
+use pyo3::{prelude::*, types::PySlice};
+
+use crate::{
+    matrix::PyMatrix,
+    py::{into_py_result, PyOutput},
+    Axis, MatrixBuf, NormKind, OpChain, Operation, PieceOfWork, Shape, SliceRange, StrompyResult,
+};
+
+/// A [PieceOfWork] under construction, from NumPy arrays or `Matrix` objects.
+/// Each operation method appends an operation and returns the builder itself,
+/// so that calls can be chained:
+///
+/// ```python
+/// res = strompy.Work(a).matmul(b).scale(0.5).exec(numpy=True)
+/// ```
+#[pyclass(name = "Work")]
+#[derive(Clone)]
+pub struct WorkBuilder {
+    work: PieceOfWork,
+}
+
+impl WorkBuilder {
+    fn push(mut slf: PyRefMut<'_, Self>, op: Operation) -> PyResult<PyRefMut<'_, Self>> {
+        slf.work.op.push(op)?;
+        Ok(slf)
+    }
+}
+
+/// A matrix passed from Python, either as a two-dimensional NumPy array
+/// or as a [PyMatrix] an earlier piece of work resulted in
+#[derive(FromPyObject)]
+enum MatrixArg<'py> {
+    Matrix(Bound<'py, PyMatrix>),
+    Array(PyReadonlyArray2<'py, f64>),
+}
+
+impl MatrixArg<'_> {
+    fn into_matrix(self) -> StrompyResult<MatrixBuf> {
+        match self {
+            MatrixArg::Matrix(m) => Ok(m.get().matrix().clone()),
+            MatrixArg::Array(array) => MatrixBuf::from_ndarray(array),
+        }
+    }
+}
+
+/// The range a Python `slice` selects, or every row or column if there is none
+fn slice_range(slice: Option<&Bound<'_, PySlice>>) -> PyResult<SliceRange> {
+    let Some(slice) = slice else {
+        return Ok(SliceRange::default());
+    };
+    Ok(SliceRange {
+        start: slice.getattr("start")?.extract()?,
+        stop: slice.getattr("stop")?.extract()?,
+        step: slice.getattr("step")?.extract()?,
+    })
+}
+
+#[pymethods]
+impl WorkBuilder {
+    #[new]
+    fn new(lhs: MatrixArg<'_>) -> PyResult<Self> {
+        let work = PieceOfWork {
+            lhs: lhs.into_matrix()?.into(),
+            op: OpChain::new(),
+            lhs_key: "lhs",
+            name: None,
+        };
+        Ok(Self { work })
+    }
+
+    fn dot<'py>(slf: PyRefMut<'py, Self>, rhs: MatrixArg<'py>) -> PyResult<PyRefMut<'py, Self>> {
+        let rhs = rhs.into_matrix()?;
+        Self::push(slf, Operation::Dot { rhs: rhs.into() })
+    }
+
+    fn matmul<'py>(slf: PyRefMut<'py, Self>, rhs: MatrixArg<'py>) -> PyResult<PyRefMut<'py, Self>> {
+        let rhs = rhs.into_matrix()?;
+        Self::push(slf, Operation::MatMul { rhs: rhs.into() })
+    }
+
+    fn add<'py>(slf: PyRefMut<'py, Self>, rhs: MatrixArg<'py>) -> PyResult<PyRefMut<'py, Self>> {
+        let rhs = rhs.into_matrix()?;
+        Self::push(slf, Operation::Add { rhs: rhs.into() })
+    }
+
+    fn sub<'py>(slf: PyRefMut<'py, Self>, rhs: MatrixArg<'py>) -> PyResult<PyRefMut<'py, Self>> {
+        let rhs = rhs.into_matrix()?;
+        Self::push(slf, Operation::Sub { rhs: rhs.into() })
+    }
+
+    fn scale(slf: PyRefMut<'_, Self>, factor: f64) -> PyResult<PyRefMut<'_, Self>> {
+        Self::push(slf, Operation::Scale { factor })
+    }
+
+    fn transpose(slf: PyRefMut<'_, Self>) -> PyResult<PyRefMut<'_, Self>> {
+        Self::push(slf, Operation::Transpose)
+    }
+
+    fn inverse(slf: PyRefMut<'_, Self>) -> PyResult<PyRefMut<'_, Self>> {
+        Self::push(slf, Operation::Inverse)
+    }
+
+    fn determinant(slf: PyRefMut<'_, Self>) -> PyResult<PyRefMut<'_, Self>> {
+        Self::push(slf, Operation::Determinant)
+    }
+
+    fn solve<'py>(slf: PyRefMut<'py, Self>, rhs: MatrixArg<'py>) -> PyResult<PyRefMut<'py, Self>> {
+        let rhs = rhs.into_matrix()?;
+        Self::push(slf, Operation::Solve { rhs: rhs.into() })
+    }
+
+    fn cholesky(slf: PyRefMut<'_, Self>) -> PyResult<PyRefMut<'_, Self>> {
+        Self::push(slf, Operation::Cholesky)
+    }
+
+    /// Decompose the matrix into `p`, `l` and `u`. Executing the work
+    /// results in a dict holding these factors by name.
+    fn lu(slf: PyRefMut<'_, Self>) -> PyResult<PyRefMut<'_, Self>> {
+        Self::push(slf, Operation::Lu)
+    }
+
+    /// Decompose the matrix into `q` and `r`. Executing the work
+    /// results in a dict holding these factors by name.
+    fn qr(slf: PyRefMut<'_, Self>) -> PyResult<PyRefMut<'_, Self>> {
+        Self::push(slf, Operation::Qr)
+    }
+
+    /// Decompose the matrix into `u`, `s` and `v_t`. Executing the work
+    /// results in a dict holding these factors by name.
+    fn svd(slf: PyRefMut<'_, Self>) -> PyResult<PyRefMut<'_, Self>> {
+        Self::push(slf, Operation::Svd)
+    }
+
+    fn abs(slf: PyRefMut<'_, Self>) -> PyResult<PyRefMut<'_, Self>> {
+        Self::push(slf, Operation::Abs)
+    }
+
+    fn exp(slf: PyRefMut<'_, Self>) -> PyResult<PyRefMut<'_, Self>> {
+        Self::push(slf, Operation::Exp)
+    }
+
+    fn log(slf: PyRefMut<'_, Self>) -> PyResult<PyRefMut<'_, Self>> {
+        Self::push(slf, Operation::Log)
+    }
+
+    fn sqrt(slf: PyRefMut<'_, Self>) -> PyResult<PyRefMut<'_, Self>> {
+        Self::push(slf, Operation::Sqrt)
+    }
+
+    fn pow(slf: PyRefMut<'_, Self>, exponent: f64) -> PyResult<PyRefMut<'_, Self>> {
+        Self::push(slf, Operation::Pow { exponent })
+    }
+
+    #[pyo3(signature = (min = None, max = None))]
+    fn clamp(
+        slf: PyRefMut<'_, Self>,
+        min: Option<f64>,
+        max: Option<f64>,
+    ) -> PyResult<PyRefMut<'_, Self>> {
+        Self::push(slf, Operation::Clamp { min, max })
+    }
+
+    /// Sum the elements along `axis`, which is one of `"all"`, `"rows"`
+    /// or `"cols"`. The other reductions take `axis` in the same way.
+    #[pyo3(signature = (axis = "all"))]
+    fn sum<'py>(slf: PyRefMut<'py, Self>, axis: &str) -> PyResult<PyRefMut<'py, Self>> {
+        let axis = Axis::try_from(axis.to_owned())?;
+        Self::push(slf, Operation::Sum { axis })
+    }
+
+    #[pyo3(signature = (axis = "all"))]
+    fn mean<'py>(slf: PyRefMut<'py, Self>, axis: &str) -> PyResult<PyRefMut<'py, Self>> {
+        let axis = Axis::try_from(axis.to_owned())?;
+        Self::push(slf, Operation::Mean { axis })
+    }
+
+    #[pyo3(signature = (axis = "all"))]
+    fn min<'py>(slf: PyRefMut<'py, Self>, axis: &str) -> PyResult<PyRefMut<'py, Self>> {
+        let axis = Axis::try_from(axis.to_owned())?;
+        Self::push(slf, Operation::Min { axis })
+    }
+
+    #[pyo3(signature = (axis = "all"))]
+    fn max<'py>(slf: PyRefMut<'py, Self>, axis: &str) -> PyResult<PyRefMut<'py, Self>> {
+        let axis = Axis::try_from(axis.to_owned())?;
+        Self::push(slf, Operation::Max { axis })
+    }
+
+    /// Compute the norm `kind`, which is one of `"l1"`, `"l2"` or `"inf"`
+    #[pyo3(signature = (kind = "l2", axis = "all"))]
+    fn norm<'py>(
+        slf: PyRefMut<'py, Self>,
+        kind: &str,
+        axis: &str,
+    ) -> PyResult<PyRefMut<'py, Self>> {
+        let kind = NormKind::try_from(kind.to_owned())?;
+        let axis = Axis::try_from(axis.to_owned())?;
+        Self::push(slf, Operation::Norm { axis, kind })
+    }
+
+    /// Select a block of the matrix, given a Python `slice` of its rows and one of
+    /// its columns, like `.slice(rows=slice(0, 4, 2))`. Indices cannot be negative.
+    #[pyo3(signature = (rows = None, cols = None))]
+    fn slice<'py>(
+        slf: PyRefMut<'py, Self>,
+        rows: Option<&Bound<'py, PySlice>>,
+        cols: Option<&Bound<'py, PySlice>>,
+    ) -> PyResult<PyRefMut<'py, Self>> {
+        let (rows, cols) = (slice_range(rows)?, slice_range(cols)?);
+        Self::push(slf, Operation::Slice { rows, cols })
+    }
+
+    fn reshape(slf: PyRefMut<'_, Self>, rows: usize, cols: usize) -> PyResult<PyRefMut<'_, Self>> {
+        let shape = Shape { rows, cols };
+        Self::push(slf, Operation::Reshape { shape })
+    }
+
+    fn hstack<'py>(slf: PyRefMut<'py, Self>, rhs: MatrixArg<'py>) -> PyResult<PyRefMut<'py, Self>> {
+        let rhs = rhs.into_matrix()?;
+        Self::push(slf, Operation::HStack { rhs: rhs.into() })
+    }
+
+    fn vstack<'py>(slf: PyRefMut<'py, Self>, rhs: MatrixArg<'py>) -> PyResult<PyRefMut<'py, Self>> {
+        let rhs = rhs.into_matrix()?;
+        Self::push(slf, Operation::VStack { rhs: rhs.into() })
+    }
+
+    fn diag(slf: PyRefMut<'_, Self>) -> PyResult<PyRefMut<'_, Self>> {
+        Self::push(slf, Operation::Diag)
+    }
+
+    /// Execute the work built so far. The builder itself is left untouched,
+    /// so it can be extended and executed again. The GIL is released
+    /// while the work executes. With `matrix=True`, the result is a `Matrix`,
+    /// which can be passed to new work as is.
+    #[pyo3(signature = (*, numpy = false, matrix = false))]
+    fn exec(&self, py: Python<'_>, numpy: bool, matrix: bool) -> PyResult<PyObject> {
+        let output = PyOutput::new(numpy, matrix)?;
+        let work = self.work.clone();
+        let res = py.allow_threads(|| work.exec())?;
+        into_py_result(py, res, output)
+    }
+}
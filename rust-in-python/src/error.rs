@@ -3,43 +3,595 @@ use std::{
     num::{ParseFloatError, ParseIntError},
 };
 
-use pyo3::{
-    exceptions::{PyException, PyValueError},
-    prelude::*,
-};
+use futures::AsyncRead;
+#[cfg(feature = "python")]
+use pyo3::prelude::*;
+use struson::reader::{json_path::JsonPathPiece, JsonReader, JsonStreamReader};
+
+use crate::Shape;
 
 #[derive(Debug)]
 pub enum StrompyError {
-    Json(&'static str),
+    /// A required key was not present in a JSON object
+    MissingKey(&'static str),
+    /// A key occurred more than once in a JSON object
+    DuplicateKey(&'static str),
+    /// Two keys that cannot be combined occurred in the same JSON object
+    ConflictingKeys(&'static str, &'static str),
+    /// An [Operation](crate::Operation) code was not recognized
+    UnknownOperation(String),
+    /// A key was given a value that is not one of those it accepts
+    InvalidValue {
+        key: &'static str,
+        value: String,
+    },
+    /// A reference to a named result that does not exist (yet)
+    DanglingRef(String),
     Struson(struson::reader::ReaderError),
     ParseFloat(ParseFloatError),
     ParseInt(std::num::ParseIntError),
+    /// Input did not fit in the configured storage capacity
+    Capacity { capacity: usize },
+    /// Values in the input are nested more than `max_depth` levels deep
+    TooDeep { max_depth: usize },
+    Serde(serde_json::Error),
+    /// Input could not be decoded as CBOR
+    Cbor(ciborium::de::Error<std::io::Error>),
+    /// Input could not be decoded as MessagePack
+    MessagePack(rmp_serde::decode::Error),
+    /// Matrix data of length `len` is empty, or cannot be divided into rows of `n` columns
+    InvalidShape { len: usize, n: usize },
+    /// An operation cannot be applied to matrices of these shapes
+    ShapeMismatch {
+        op: &'static str,
+        lhs: Shape,
+        rhs: Shape,
+    },
+    /// A range of rows or columns is empty, or extends beyond the `len` rows or
+    /// columns of the matrix it is applied to. `axis` is either `"rows"` or `"cols"`.
+    InvalidRange {
+        axis: &'static str,
+        start: usize,
+        stop: usize,
+        len: usize,
+    },
+    /// A matrix cannot be reshaped, as the new shape holds a different number of elements
+    InvalidReshape {
+        from: Shape,
+        to: Shape,
+    },
+    /// An operation that needs a square matrix was applied to a matrix of this shape
+    NotSquare {
+        op: &'static str,
+        shape: Shape,
+    },
+    /// An operation has no result, as the matrix it was applied to is singular
+    Singular {
+        op: &'static str,
+    },
+    /// An operation that needs a symmetric positive-definite matrix was applied to
+    /// a matrix that is not
+    NotPositiveDefinite {
+        op: &'static str,
+    },
+    /// An iterative algorithm did not converge
+    NoConvergence {
+        op: &'static str,
+    },
+    /// The result of a decomposition, which consists of several
+    /// matrices, was used where a single matrix is needed
+    NotAMatrix {
+        op: &'static str,
+    },
+    /// The result of an operation would have more rows, columns
+    /// or elements than can be counted
+    ShapeOverflow {
+        op: &'static str,
+    },
+    /// A reader was used again after it returned an error
+    Poisoned,
+    /// A reader or writer was used after it was closed
+    Closed,
+    /// Reading was cancelled
+    Cancelled,
+    /// The sender of the input went away before the input was complete
+    SenderClosed,
+    /// Writing output failed
+    Io(std::io::Error),
+    /// A number cannot be represented in JSON, like NaN or infinity
+    UnrepresentableNumber(String),
+    /// Another error, along with the location in the input at which it occurred
+    Located(Box<StrompyError>, Location),
+}
+
+impl StrompyError {
+    /// Attach the location in the input at which this error occurred,
+    /// unless it already has one
+    pub fn at(self, location: Location) -> Self {
+        match self {
+            e @ StrompyError::Located(..) => e,
+            e => StrompyError::Located(Box::new(e), location),
+        }
+    }
+
+    /// Prefix the JSON path of this error with `piece`. Used to turn a path that is
+    /// relative to some value into a path relative to the value that contains it.
+    pub fn within(self, piece: PathPiece) -> Self {
+        match self {
+            StrompyError::Located(e, mut location) => {
+                location.path.insert(0, piece);
+                StrompyError::Located(e, location)
+            }
+            e => e.at(Location {
+                path: vec![piece],
+                ..Default::default()
+            }),
+        }
+    }
+
+    /// Locate this error relative to the value at `location`, in which it
+    /// occurred. An error that has only been located [within](Self::within)
+    /// that value so far has its path prefixed with the path of the value.
+    pub fn relative_to(self, location: Location) -> Self {
+        match self {
+            StrompyError::Located(e, relative) if relative.offset.is_none() => {
+                let mut path = location.path;
+                path.extend(relative.path);
+                StrompyError::Located(e, Location { path, ..location })
+            }
+            e => e.at(location),
+        }
+    }
+
+    /// The error itself, without any location information
+    pub fn kind(&self) -> &StrompyError {
+        match self {
+            StrompyError::Located(e, _) => e.kind(),
+            e => e,
+        }
+    }
+
+    /// The location in the input at which this error occurred, if known
+    pub fn location(&self) -> Option<&Location> {
+        match self {
+            StrompyError::Located(_, location) => Some(location),
+            _ => None,
+        }
+    }
+}
+
+/// A single step in a JSON path
+#[derive(Debug, Clone, PartialEq)]
+pub enum PathPiece {
+    /// An item in an array
+    Index(usize),
+    /// A member of an object
+    Key(String),
+}
+
+impl From<&JsonPathPiece> for PathPiece {
+    fn from(piece: &JsonPathPiece) -> Self {
+        match piece {
+            JsonPathPiece::ArrayItem(i) => PathPiece::Index(*i as usize),
+            JsonPathPiece::ObjectMember(name) => PathPiece::Key(name.clone()),
+        }
+    }
+}
+
+/// A location in the input data
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Location {
+    /// The JSON path of the value at this location
+    pub path: Vec<PathPiece>,
+    /// The offset in bytes from the start of the input, starting at 0
+    pub offset: Option<u64>,
+    /// The line number, starting at 1
+    pub line: Option<u64>,
+    /// The column number, starting at 1
+    pub column: Option<u64>,
+}
+
+impl Location {
+    /// The location a [JsonStreamReader] is currently at
+    pub fn of_reader<R: AsyncRead + Unpin>(reader: &JsonStreamReader<R>) -> Self {
+        let position = reader.current_position(true);
+        Self {
+            path: position
+                .path
+                .map(|path| path.iter().map(PathPiece::from).collect())
+                .unwrap_or_default(),
+            offset: position.data_pos,
+            line: position.line_pos.as_ref().map(|pos| pos.line + 1),
+            column: position.line_pos.as_ref().map(|pos| pos.column + 1),
+        }
+    }
+
+    /// The location of a value deserialized by serde, given just its path
+    pub fn of_path(path: &serde_path_to_error::Path) -> Self {
+        use serde_path_to_error::Segment;
+
+        let path = path
+            .iter()
+            .map(|segment| match segment {
+                Segment::Seq { index } => PathPiece::Index(*index),
+                Segment::Map { key } => PathPiece::Key(key.clone()),
+                Segment::Enum { variant } => PathPiece::Key(variant.clone()),
+                Segment::Unknown => PathPiece::Key("?".to_owned()),
+            })
+            .collect();
+
+        Self {
+            path,
+            ..Default::default()
+        }
+    }
+
+    /// The location at which deserializing `input` with serde failed
+    pub fn of_serde(e: &serde_path_to_error::Error<serde_json::Error>, input: &[u8]) -> Self {
+        let Self { path, .. } = Self::of_path(e.path());
+
+        // serde_json reports a line of 0 if the location is unknown
+        let (line, column) = (e.inner().line(), e.inner().column());
+        if line == 0 {
+            return Self {
+                path,
+                ..Default::default()
+            };
+        }
+
+        let line_start = match line {
+            1 => 0,
+            _ => input
+                .iter()
+                .enumerate()
+                .filter(|&(_, &b)| b == b'\n')
+                .nth(line - 2)
+                .map_or(0, |(i, _)| i + 1),
+        };
+
+        Self {
+            path,
+            offset: Some((line_start + column.saturating_sub(1)) as u64),
+            line: Some(line as u64),
+            column: Some(column as u64),
+        }
+    }
+
+    /// The JSON path of this location, formatted like `$[3].op[1].rhs.d[7]`
+    pub fn json_path(&self) -> String {
+        let mut path = String::from("$");
+        for piece in &self.path {
+            match piece {
+                PathPiece::Index(i) => path.push_str(&format!("[{i}]")),
+                PathPiece::Key(key) => path.push_str(&format!(".{key}")),
+            }
+        }
+        path
+    }
+}
+
+impl Display for Location {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.json_path())?;
+        if let (Some(line), Some(column)) = (self.line, self.column) {
+            write!(f, ", line {line}, column {column}")?;
+        }
+        if let Some(offset) = self.offset {
+            write!(f, ", byte offset {offset}")?;
+        }
+        Ok(())
+    }
 }
 
 impl Display for StrompyError {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         match self {
-            StrompyError::Json(e) => write!(f, r#"Unexpected key encountered, expected "{e}""#),
+            StrompyError::MissingKey(key) => write!(f, r#"Missing key "{key}""#),
+            StrompyError::DuplicateKey(key) => write!(f, r#"Duplicate key "{key}""#),
+            StrompyError::ConflictingKeys(key, other) => {
+                write!(f, r#"Key "{key}" cannot be combined with key "{other}""#)
+            }
+            StrompyError::UnknownOperation(code) => write!(f, r#"Unknown operation code "{code}""#),
+            StrompyError::InvalidValue { key, value } => {
+                write!(f, r#"Invalid value "{value}" for key "{key}""#)
+            }
+            StrompyError::DanglingRef(name) => write!(
+                f,
+                r#"Reference to unknown result "{name}": no earlier work in this stream is named "{name}""#
+            ),
             StrompyError::Struson(e) => write!(f, "Struson error: {e}"),
             StrompyError::ParseFloat(e) => write!(f, "ParseFloat error: {e}"),
             StrompyError::ParseInt(e) => write!(f, "ParseInt error: {e}"),
+            StrompyError::Capacity { capacity } => {
+                write!(f, "Capacity error: input exceeds capacity of {capacity} items")
+            }
+            StrompyError::TooDeep { max_depth } => {
+                write!(f, "Input is nested more than {max_depth} levels deep")
+            }
+            StrompyError::Serde(e) => write!(f, "Serde error: {e}"),
+            StrompyError::Cbor(e) => write!(f, "CBOR error: {e}"),
+            StrompyError::MessagePack(e) => write!(f, "MessagePack error: {e}"),
+            StrompyError::InvalidShape { len, n } => write!(
+                f,
+                "Dimension error: cannot divide {len} elements into rows of {n} columns"
+            ),
+            StrompyError::ShapeMismatch { op, lhs, rhs } => write!(
+                f,
+                r#"Dimension error: cannot apply "{op}" to matrices of shape {lhs} and {rhs}"#
+            ),
+            StrompyError::InvalidRange {
+                axis,
+                start,
+                stop,
+                len,
+            } => write!(
+                f,
+                "Dimension error: cannot select {axis} {start}..{stop} of a matrix with {len} {axis}"
+            ),
+            StrompyError::InvalidReshape { from, to } => write!(
+                f,
+                "Dimension error: cannot reshape a matrix of shape {from} into shape {to}"
+            ),
+            StrompyError::NotSquare { op, shape } => write!(
+                f,
+                r#"Dimension error: cannot apply "{op}" to a matrix of shape {shape}, as it is not square"#
+            ),
+            StrompyError::Singular { op } => {
+                write!(f, r#"Linear algebra error: cannot apply "{op}" to a singular matrix"#)
+            }
+            StrompyError::NotPositiveDefinite { op } => write!(
+                f,
+                r#"Linear algebra error: cannot apply "{op}" to a matrix that is not symmetric positive-definite"#
+            ),
+            StrompyError::NoConvergence { op } => {
+                write!(f, r#"Linear algebra error: "{op}" did not converge"#)
+            }
+            StrompyError::NotAMatrix { op } => write!(
+                f,
+                r#"Dimension error: the result of "{op}" consists of several matrices, and cannot be used as a single matrix"#
+            ),
+            StrompyError::ShapeOverflow { op } => write!(
+                f,
+                r#"Dimension error: the result of "{op}" has more elements than can be counted"#
+            ),
+            StrompyError::Poisoned => write!(f, "Reader cannot be used after an earlier error"),
+            StrompyError::Closed => write!(f, "Cannot be used after it was closed"),
+            StrompyError::Cancelled => write!(f, "Reading was cancelled"),
+            StrompyError::SenderClosed => write!(
+                f,
+                "Input ended unexpectedly: the sender was closed or dropped before all work was sent"
+            ),
+            StrompyError::Io(e) => write!(f, "IO error: {e}"),
+            StrompyError::UnrepresentableNumber(number) => {
+                write!(f, "Number {number} cannot be represented in JSON")
+            }
+            StrompyError::Located(e, location) => write!(f, "{e} (at {location})"),
         }
     }
 }
 
+/// The Python exception classes raised by strompy. All of them derive from
+/// [exceptions::StrompyError], and carry `path`, `offset`, `line` and `column`
+/// attributes describing where in the input the error occurred, if known.
+#[cfg(feature = "python")]
+pub mod exceptions {
+    use pyo3::{create_exception, exceptions::PyException};
+
+    create_exception!(
+        strompy,
+        StrompyError,
+        PyException,
+        "Base class of all errors raised by strompy."
+    );
+    create_exception!(
+        strompy,
+        JsonSyntaxError,
+        StrompyError,
+        "The input is not well-formed JSON, or not structured as expected, like a value \
+        of the wrong type."
+    );
+    create_exception!(
+        strompy,
+        DecodeError,
+        StrompyError,
+        "The input is not well-formed CBOR or MessagePack."
+    );
+    create_exception!(
+        strompy,
+        UnexpectedKeyError,
+        StrompyError,
+        "A key is missing, duplicated or conflicts with another key. The `expected` attribute \
+        holds the missing key, and the `found` attribute holds the duplicated or conflicting key."
+    );
+    create_exception!(
+        strompy,
+        CapacityError,
+        StrompyError,
+        "The input does not fit in the storage capacity given by the `capacity` attribute."
+    );
+    create_exception!(
+        strompy,
+        DimensionError,
+        StrompyError,
+        "The shape of a matrix is invalid, or does not fit the operation given by the \
+        `operation` attribute. In the latter case, the `lhs_shape` and `rhs_shape` \
+        attributes hold the shapes of both operands."
+    );
+    create_exception!(
+        strompy,
+        LinAlgError,
+        StrompyError,
+        "The operation given by the `operation` attribute has no result for its matrix, \
+        as the matrix is singular or not positive-definite, or the algorithm did not converge."
+    );
+    create_exception!(
+        strompy,
+        UnknownReferenceError,
+        StrompyError,
+        "A reference to a result that no earlier work in the stream was named as. \
+        The `name` attribute holds the name referred to."
+    );
+    create_exception!(
+        strompy,
+        UnknownOperationError,
+        StrompyError,
+        "The operation code given by the `code` attribute is not supported."
+    );
+    create_exception!(
+        strompy,
+        InvalidNumberError,
+        StrompyError,
+        "A number could not be parsed into the expected numeric type, \
+        or could not be represented in the output."
+    );
+}
+
+#[cfg(feature = "python")]
 impl IntoPy<Py<PyAny>> for StrompyError {
     fn into_py(self, py: pyo3::prelude::Python<'_>) -> Py<PyAny> {
         self.to_string().into_py(py)
     }
 }
 
+#[cfg(feature = "python")]
 impl From<StrompyError> for pyo3::PyErr {
     fn from(e: StrompyError) -> Self {
-        match e {
-            StrompyError::ParseFloat(e) => PyValueError::new_err(e),
-            StrompyError::ParseInt(e) => PyValueError::new_err(e),
-            e => PyException::new_err(e),
+        use exceptions::{
+            CapacityError, DecodeError, DimensionError, InvalidNumberError, JsonSyntaxError,
+            LinAlgError, UnexpectedKeyError, UnknownOperationError, UnknownReferenceError,
+        };
+
+        let msg = e.to_string();
+        let (mut e, mut location) = (e, None);
+        while let StrompyError::Located(inner, inner_location) = e {
+            location.get_or_insert(inner_location);
+            e = *inner;
         }
+
+        Python::with_gil(|py| {
+            let (err, attrs): (PyErr, Vec<(&str, PyObject)>) = match e {
+                StrompyError::MissingKey(key) => (
+                    UnexpectedKeyError::new_err(msg),
+                    vec![("expected", key.into_py(py)), ("found", py.None())],
+                ),
+                StrompyError::DuplicateKey(key) => (
+                    UnexpectedKeyError::new_err(msg),
+                    vec![("expected", py.None()), ("found", key.into_py(py))],
+                ),
+                StrompyError::ConflictingKeys(_, other) => (
+                    UnexpectedKeyError::new_err(msg),
+                    vec![("expected", py.None()), ("found", other.into_py(py))],
+                ),
+                StrompyError::DanglingRef(name) => (
+                    UnknownReferenceError::new_err(msg),
+                    vec![("name", name.into_py(py))],
+                ),
+                StrompyError::UnknownOperation(code) => (
+                    UnknownOperationError::new_err(msg),
+                    vec![("code", code.into_py(py))],
+                ),
+                StrompyError::InvalidValue { key, value } => (
+                    exceptions::StrompyError::new_err(msg),
+                    vec![("key", key.into_py(py)), ("value", value.into_py(py))],
+                ),
+                StrompyError::Capacity { capacity } => (
+                    CapacityError::new_err(msg),
+                    vec![("capacity", capacity.into_py(py))],
+                ),
+                StrompyError::InvalidShape { len, n } => (
+                    DimensionError::new_err(msg),
+                    vec![("length", len.into_py(py)), ("n", n.into_py(py))],
+                ),
+                StrompyError::ShapeMismatch { op, lhs, rhs } => (
+                    DimensionError::new_err(msg),
+                    vec![
+                        ("operation", op.into_py(py)),
+                        ("lhs_shape", (lhs.rows, lhs.cols).into_py(py)),
+                        ("rhs_shape", (rhs.rows, rhs.cols).into_py(py)),
+                    ],
+                ),
+                StrompyError::InvalidRange {
+                    axis,
+                    start,
+                    stop,
+                    len,
+                } => (
+                    DimensionError::new_err(msg),
+                    vec![
+                        ("axis", axis.into_py(py)),
+                        ("start", start.into_py(py)),
+                        ("stop", stop.into_py(py)),
+                        ("length", len.into_py(py)),
+                    ],
+                ),
+                StrompyError::InvalidReshape { from, to } => (
+                    DimensionError::new_err(msg),
+                    vec![
+                        ("lhs_shape", (from.rows, from.cols).into_py(py)),
+                        ("shape", (to.rows, to.cols).into_py(py)),
+                    ],
+                ),
+                StrompyError::NotSquare { op, shape } => (
+                    DimensionError::new_err(msg),
+                    vec![
+                        ("operation", op.into_py(py)),
+                        ("lhs_shape", (shape.rows, shape.cols).into_py(py)),
+                    ],
+                ),
+                StrompyError::NotAMatrix { op } | StrompyError::ShapeOverflow { op } => (
+                    DimensionError::new_err(msg),
+                    vec![("operation", op.into_py(py))],
+                ),
+                StrompyError::Singular { op }
+                | StrompyError::NotPositiveDefinite { op }
+                | StrompyError::NoConvergence { op } => (
+                    LinAlgError::new_err(msg),
+                    vec![("operation", op.into_py(py))],
+                ),
+                StrompyError::ParseFloat(_)
+                | StrompyError::ParseInt(_)
+                | StrompyError::UnrepresentableNumber(_) => {
+                    (InvalidNumberError::new_err(msg), vec![])
+                }
+                // Streaming and in-memory input report malformed JSON and values of
+                // the wrong type alike, whether struson or serde detects them
+                StrompyError::Struson(_)
+                | StrompyError::Serde(_)
+                | StrompyError::TooDeep { .. } => (JsonSyntaxError::new_err(msg), vec![]),
+                // Errors in the structure of well-formed input, like missing keys
+                StrompyError::Cbor(ciborium::de::Error::Semantic(..))
+                | StrompyError::MessagePack(rmp_serde::decode::Error::Syntax(_)) => {
+                    (exceptions::StrompyError::new_err(msg), vec![])
+                }
+                StrompyError::Cbor(_) | StrompyError::MessagePack(_) => {
+                    (DecodeError::new_err(msg), vec![])
+                }
+                StrompyError::Io(e) => (e.into(), vec![]),
+                StrompyError::Poisoned
+                | StrompyError::Closed
+                | StrompyError::Cancelled
+                | StrompyError::SenderClosed
+                | StrompyError::Located(..) => {
+                    (exceptions::StrompyError::new_err(msg), vec![])
+                }
+            };
+
+            let location = location.as_ref();
+            let location_attrs = [
+                ("path", location.map(Location::json_path).into_py(py)),
+                ("offset", location.and_then(|l| l.offset).into_py(py)),
+                ("line", location.and_then(|l| l.line).into_py(py)),
+                ("column", location.and_then(|l| l.column).into_py(py)),
+            ];
+
+            let value = err.value_bound(py);
+            for (name, attr) in attrs.into_iter().chain(location_attrs) {
+                // Setting attributes on a fresh exception instance does not fail
+                let _ = value.setattr(name, attr);
+            }
+
+            err
+        })
     }
 }
 
@@ -49,6 +601,29 @@ impl From<struson::reader::ReaderError> for StrompyError {
     }
 }
 
+impl From<std::io::Error> for StrompyError {
+    fn from(e: std::io::Error) -> Self {
+        Self::Io(e)
+    }
+}
+
+impl From<struson::writer::JsonNumberError> for StrompyError {
+    fn from(e: struson::writer::JsonNumberError) -> Self {
+        match e {
+            struson::writer::JsonNumberError::InvalidNumber(number) => {
+                Self::UnrepresentableNumber(number)
+            }
+            struson::writer::JsonNumberError::IoError(e) => Self::Io(e),
+        }
+    }
+}
+
+impl From<serde_json::Error> for StrompyError {
+    fn from(e: serde_json::Error) -> Self {
+        Self::Serde(e)
+    }
+}
+
 impl From<ParseFloatError> for StrompyError {
     fn from(e: ParseFloatError) -> Self {
         Self::ParseFloat(e)
@@ -1,17 +1,82 @@
-use error::StrompyError;
+use std::collections::HashSet;
+
+use reduce::Reduction;
+use scope::Scope;
 use futures::AsyncRead;
-use heapless::Vec as HeaplessVec;
 use nalgebra as na;
+#[cfg(feature = "python")]
 use pyo3::{types::PyList, Py, Python};
-use struson::reader::{JsonReader, JsonStreamReader};
-
+use storage::Storage;
+use struson::reader::{JsonReader, JsonStreamReader, ReaderError, ReaderSettings};
+use validate::Validator;
+pub use binary::{exec_binary, BinaryFormat, BinaryWorkReader};
+pub use error::{Location, PathPiece, StrompyError};
+pub use reduce::{Axis, NormKind};
+pub use reshape::SliceRange;
+pub use validate::{validate, validate_binary};
+pub use writer::{OutputMode, WorkWriter};
+
+mod binary;
+#[cfg(feature = "python")]
+mod builder;
 mod error;
+mod linalg;
+#[cfg(feature = "python")]
+mod matrix;
+mod reduce;
+mod reshape;
+mod scope;
+mod storage;
+mod validate;
+mod writer;
 
 type StrompyResult<T> = core::result::Result<T, StrompyError>;
 
+/// The maximum number of elements a [MatrixBuf] can hold
+/// if the `heap` feature is disabled
+pub const MATRIX_CAPACITY: usize = 6 * 6;
+
+/// The maximum number of operations in a [PieceOfWork]
+/// if the `heap` feature is disabled
+pub const OP_CHAIN_CAPACITY: usize = 5;
+
+/// The maximum number of levels to which values in the input can be nested,
+/// which bounds how deep reading nested operands recurses. This is the same
+/// as the limit serde_json applies when work is read fully into memory.
+pub const MAX_DEPTH: usize = 128;
+
+/// A chain of [Operation]s to be applied one after another
+type OpChain = Storage<Operation, OP_CHAIN_CAPACITY>;
+
+/// Set a field that was read from a JSON object, failing if it was already set
+fn set_once<T>(field: &mut Option<T>, key: &'static str, value: T) -> StrompyResult<()> {
+    match field.replace(value) {
+        Some(_) => Err(StrompyError::DuplicateKey(key)),
+        None => Ok(()),
+    }
+}
+
+/// Take a field that was read from a JSON object, failing if it was never set
+fn required<T>(field: Option<T>, key: &'static str) -> StrompyResult<T> {
+    field.ok_or(StrompyError::MissingKey(key))
+}
+
+/// Validate a field that was read fully into memory with `f`,
+/// locating any error by the `key` the field was given under
+fn convert<T, U>(
+    field: Option<T>,
+    key: &str,
+    f: impl FnOnce(T) -> StrompyResult<U>,
+) -> StrompyResult<Option<U>> {
+    field
+        .map(f)
+        .transpose()
+        .map_err(|e| e.within(PathPiece::Key(key.to_owned())))
+}
+
 /// A [nalgebra::Matrix] that is backed by some other means of storage.
-/// Allows for backing [nalgebra::Matrix] with some stack-based
-/// storage, like [HeaplessVec]
+/// Allows for backing [nalgebra::Matrix] with either heap-allocated
+/// or stack-based storage, see [Storage]
 pub type MatrixView<'buf> = na::Matrix<
     f64,
     na::Dyn,
@@ -19,18 +84,150 @@ pub type MatrixView<'buf> = na::Matrix<
     na::ViewStorage<'buf, f64, na::Dyn, na::Dyn, na::Const<1>, na::Dyn>,
 >;
 
-/// A buffer into which matrix data can be stored
-#[derive(serde::Deserialize, Debug)]
+/// Mutable counterpart of [MatrixView]
+pub type MatrixViewMut<'buf> = na::Matrix<
+    f64,
+    na::Dyn,
+    na::Dyn,
+    na::ViewStorageMut<'buf, f64, na::Dyn, na::Dyn, na::Const<1>, na::Dyn>,
+>;
+
+/// The number of rows and columns of a matrix, given as `[rows, cols]` in the input
+#[derive(serde::Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(from = "[usize; 2]")]
+pub struct Shape {
+    pub rows: usize,
+    pub cols: usize,
+}
+
+impl std::fmt::Display for Shape {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}x{}", self.rows, self.cols)
+    }
+}
+
+impl Shape {
+    /// The number of elements of a matrix of this shape,
+    /// or `None` if there are more than fit in a `usize`
+    pub fn size(self) -> Option<usize> {
+        self.rows.checked_mul(self.cols)
+    }
+}
+
+/// The order in which the data of a matrix lists its elements, given as `"order"`.
+/// Defaults to [Order::Col].
+#[derive(serde::Deserialize, Debug, Clone, Copy, Default, PartialEq, Eq)]
+#[serde(try_from = "String")]
+pub enum Order {
+    /// Row by row, given as `"row"`
+    Row,
+    /// Column by column, given as `"col"`. This is how a [MatrixBuf]
+    /// stores its elements, so data in this order is used as is.
+    #[default]
+    Col,
+}
+
+impl TryFrom<String> for Order {
+    type Error = StrompyError;
+
+    fn try_from(value: String) -> StrompyResult<Self> {
+        match value.as_str() {
+            "row" => Ok(Order::Row),
+            "col" => Ok(Order::Col),
+            _ => Err(StrompyError::InvalidValue {
+                key: "order",
+                value,
+            }),
+        }
+    }
+}
+
+/// A buffer into which matrix data can be stored.
+/// The elements are stored column by column, see [Order::Col].
+#[derive(serde::Deserialize, Debug, Clone, PartialEq)]
+#[serde(try_from = "RawMatrixBuf")]
 pub struct MatrixBuf {
-    d: HeaplessVec<f64, { 6 * 6 }>,
+    d: Storage<f64, MATRIX_CAPACITY>,
     n: usize,
 }
 
+/// A [MatrixBuf] as it occurs in the input, before its shape is validated
+#[derive(serde::Deserialize)]
+struct RawMatrixBuf {
+    d: Storage<f64, MATRIX_CAPACITY>,
+    n: usize,
+    #[serde(default)]
+    order: Order,
+}
+
+impl TryFrom<RawMatrixBuf> for MatrixBuf {
+    type Error = StrompyError;
+
+    fn try_from(RawMatrixBuf { d, n, order }: RawMatrixBuf) -> StrompyResult<Self> {
+        Self::with_order(d, n, order)
+    }
+}
+
 impl MatrixBuf {
+    /// Create a [MatrixBuf] from data `d` with `n` columns, failing if the data
+    /// is empty or cannot be divided into rows of that length. Operations like
+    /// [Operation::Svd] cannot be applied to empty matrices, so there are none.
+    fn new(d: Storage<f64, MATRIX_CAPACITY>, n: usize) -> StrompyResult<Self> {
+        if n == 0 || d.is_empty() || d.len() % n != 0 {
+            return Err(StrompyError::InvalidShape { len: d.len(), n });
+        }
+        Ok(Self { d, n })
+    }
+
+    /// Create a [MatrixBuf] from data `d` with `n` columns, which lists
+    /// the elements in `order`. See [MatrixBuf::new] for when this fails.
+    fn with_order(d: Storage<f64, MATRIX_CAPACITY>, n: usize, order: Order) -> StrompyResult<Self> {
+        match order {
+            Order::Col => Self::new(d, n),
+            Order::Row => {
+                let m = Self::new(d, n)?;
+                let Shape { rows, cols } = m.shape();
+                // Read column by column, data listed row by row is the transpose
+                let listed = MatrixView::from_slice_generic(&m.d[..], na::Dyn(cols), na::Dyn(rows));
+                let mut res = Self::zeros(rows, cols)?;
+                res.view_mut().tr_copy_from(&listed);
+                Ok(res)
+            }
+        }
+    }
+
+    pub fn shape(&self) -> Shape {
+        Shape {
+            rows: self.d.len() / self.n,
+            cols: self.n,
+        }
+    }
+
     pub fn view<'buf>(&'buf self) -> MatrixView<'buf> {
         let rows = self.d.len() / self.n;
         let cols = self.n;
-        MatrixView::from_slice_generic(&self.d, na::Dyn(rows), na::Dyn(cols))
+        MatrixView::from_slice_generic(&self.d[..], na::Dyn(rows), na::Dyn(cols))
+    }
+
+    pub fn view_mut<'buf>(&'buf mut self) -> MatrixViewMut<'buf> {
+        let rows = self.d.len() / self.n;
+        let cols = self.n;
+        MatrixViewMut::from_slice_generic(&mut self.d[..], na::Dyn(rows), na::Dyn(cols))
+    }
+
+    /// Create a [MatrixBuf] of the given shape, filled with zeros
+    fn zeros(rows: usize, cols: usize) -> StrompyResult<Self> {
+        let mut d = Storage::new();
+        d.resize(rows * cols, 0.0)?;
+        Self::new(d, cols)
+    }
+
+    /// The transpose of this matrix
+    fn transpose(&self) -> StrompyResult<Self> {
+        let a = self.view();
+        let mut res = Self::zeros(a.ncols(), a.nrows())?;
+        res.view_mut().tr_copy_from(&a);
+        Ok(res)
     }
 
     pub async fn deserialize<R: AsyncRead + Unpin>(
@@ -38,298 +235,2453 @@ impl MatrixBuf {
     ) -> StrompyResult<Self> {
         reader.begin_object().await?;
 
-        // First, read in the data
-        let "d" = reader.next_name().await? else {
-            return Err(StrompyError::Json(
-                r#"Unexpected key encountered, expected "d""#,
-            ));
-        };
-        reader.begin_array().await?;
-        let mut d = HeaplessVec::new();
+        // Keys may come in any order, so we collect
+        // all fields before constructing the buffer
+        let mut d = None;
+        let mut n = None;
+        let mut order = None;
         while reader.has_next().await? {
-            d.push(reader.next_number().await??).unwrap();
+            let name = reader.next_name().await?;
+            match name {
+                "d" => set_once(&mut d, "d", Self::deserialize_data(reader).await?)?,
+                "n" => set_once(&mut n, "n", reader.next_number().await??)?,
+                "order" => {
+                    let value = Order::try_from(reader.next_str().await?.to_owned())?;
+                    set_once(&mut order, "order", value)?
+                }
+                _ => reader.skip_value().await?,
+            }
         }
-        reader.end_array().await?;
-
-        // Then, read the number of columns
-        let "n" = reader.next_name().await? else {
-            return Err(StrompyError::Json(
-                r#"Unexpected key encountered, expected "n""#,
-            ));
-        };
-        let n = reader.next_number().await??;
 
         reader.end_object().await?;
 
-        Ok(Self { d, n })
+        let (d, n) = (required(d, "d")?, required(n, "n")?);
+        Self::with_order(d, n, order.unwrap_or_default())
     }
-}
-
-impl std::iter::IntoIterator for MatrixBuf {
-    type Item = Py<PyList>;
-
-    type IntoIter = MatrixBufIter;
 
-    fn into_iter(self) -> Self::IntoIter {
-        MatrixBufIter { buf: self, i: 0 }
+    /// Read the array of numbers that makes up the data of a matrix
+    async fn deserialize_data<R: AsyncRead + Unpin>(
+        reader: &mut JsonStreamReader<R>,
+    ) -> StrompyResult<Storage<f64, MATRIX_CAPACITY>> {
+        let mut data = Storage::new();
+        reader.begin_array().await?;
+        while reader.has_next().await? {
+            data.push(reader.next_number().await??)?;
+        }
+        reader.end_array().await?;
+        Ok(data)
     }
 }
 
-pub struct MatrixBufIter {
-    buf: MatrixBuf,
-    i: usize,
+/// The maximum number of matrices a decomposition results in, see [Factors]
+const FACTORS_CAPACITY: usize = 3;
+
+/// The matrices a decomposition results in, each identified
+/// by a name, like the `q` and `r` of a QR decomposition
+#[derive(Debug, Clone)]
+pub struct Factors {
+    /// The code of the [Operation] these are the factors of
+    op: &'static str,
+    parts: Storage<(&'static str, MatrixBuf), FACTORS_CAPACITY>,
 }
 
-impl std::iter::Iterator for MatrixBufIter {
-    type Item = Py<PyList>;
+impl Factors {
+    /// The code of the [Operation] these are the factors of
+    pub fn op(&self) -> &'static str {
+        self.op
+    }
 
-    fn next(&mut self) -> Option<Self::Item> {
-        if self.i >= self.buf.n {
-            None
-        } else {
-            let items = self.buf.d.iter().skip(self.i * self.buf.n).take(self.buf.n);
-            let item: Py<PyList> = Python::with_gil(|py| PyList::new_bound(py, items).unbind());
-            self.i += 1;
-            Some(item)
-        }
+    /// The factor called `name`, if any
+    pub fn get(&self, name: &str) -> Option<&MatrixBuf> {
+        self.iter().find(|&(part, _)| part == name).map(|(_, m)| m)
     }
 
-    fn size_hint(&self) -> (usize, Option<usize>) {
-        (self.buf.n, Some(self.buf.n))
+    /// The name of every factor along with the factor itself, in order
+    pub fn iter(&self) -> impl Iterator<Item = (&'static str, &MatrixBuf)> {
+        self.parts.iter().map(|(name, m)| (*name, m))
     }
 }
 
-impl ExactSizeIterator for MatrixBufIter {
-    fn len(&self) -> usize {
-        self.buf.n
+impl IntoIterator for Factors {
+    type Item = (&'static str, MatrixBuf);
+
+    type IntoIter =
+        <Storage<(&'static str, MatrixBuf), FACTORS_CAPACITY> as IntoIterator>::IntoIter;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.parts.into_iter()
     }
 }
 
-/// An operation that can be performed on a Matrix
-#[derive(serde::Deserialize, Debug)]
-#[serde(tag = "code", rename_all = "lowercase")]
-enum Operation {
-    /// Perform the dot product of some matrix with `rhs`
-    Dot { rhs: MatrixBuf },
-    // TODO support other operations
+/// The result of a [PieceOfWork]. Most work results in a single
+/// matrix, but decompositions result in several [Factors].
+#[derive(Debug, Clone)]
+pub enum WorkResult {
+    Matrix(MatrixBuf),
+    Factors(Factors),
 }
 
-impl Operation {
-    /// Evaluate the operation, given a [MatrixBuf]
-    fn eval(self, lhs: MatrixBuf) -> MatrixBuf {
+impl WorkResult {
+    /// The result as a single matrix, or `None` if it consists of several
+    pub fn matrix(&self) -> Option<&MatrixBuf> {
         match self {
-            Operation::Dot { rhs } => {
-                let dot = lhs.view().dot(&rhs.view());
-                MatrixBuf {
-                    d: HeaplessVec::from_slice(&[dot]).unwrap(),
-                    n: 1,
+            WorkResult::Matrix(m) => Some(m),
+            WorkResult::Factors(_) => None,
+        }
+    }
+
+    /// The result as a single matrix, failing if it consists of several
+    pub fn into_matrix(self) -> StrompyResult<MatrixBuf> {
+        match self {
+            WorkResult::Matrix(m) => Ok(m),
+            WorkResult::Factors(factors) => Err(StrompyError::NotAMatrix { op: factors.op }),
+        }
+    }
+
+    /// Store the result in `scope` under `name`. The factors of a decomposition
+    /// are stored one by one, under `name` followed by a dot and the name of the
+    /// factor, like `name.q` and `name.r`.
+    fn store(&self, name: &str, scope: &mut Scope<'_>) {
+        match self {
+            WorkResult::Matrix(m) => scope.insert(name.to_owned(), m.clone()),
+            WorkResult::Factors(factors) => {
+                for (part, m) in factors.iter() {
+                    scope.insert(format!("{name}.{part}"), m.clone());
                 }
             }
         }
     }
+}
 
-    pub async fn deserialize<R: AsyncRead + Unpin>(
-        reader: &mut JsonStreamReader<R>,
-    ) -> StrompyResult<Self> {
-        // Reads the rhs field as a MatrixBuf
-        async fn read_rhs<R: AsyncRead + Unpin>(
-            reader: &mut JsonStreamReader<R>,
-        ) -> StrompyResult<MatrixBuf> {
-            let "rhs" = reader.next_name().await? else {
-                return Err(StrompyError::Json(
-                    r#"Unexpected key encountered, expected "rhs""#,
-                ));
-            };
+impl From<MatrixBuf> for WorkResult {
+    fn from(m: MatrixBuf) -> Self {
+        Self::Matrix(m)
+    }
+}
 
-            let rhs = MatrixBuf::deserialize(reader).await?;
-            Ok(rhs)
-        }
+/// An operand of a [PieceOfWork] or [Operation]. Any operand can be
+/// given a name with `"as": "name"`, which stores its result in the [Scope]
+/// so that later operands can refer to it. Together with references, this
+/// allows for expressing a DAG in which shared subexpressions are
+/// evaluated only once.
+#[derive(serde::Deserialize, Debug, Clone)]
+#[serde(try_from = "RawOperand")]
+pub enum Operand {
+    /// Matrix data, given inline as `{"d": [...], "n": n}`, optionally
+    /// with the [Order] of the data as `"order"`
+    Inline(MatrixBuf),
+    /// The result of earlier work, given as `{"ref": "name"}`
+    Ref(String),
+    /// An operation applied to another operand, given as
+    /// `{"code": "...", "lhs": ..., ...}`, where the other fields are
+    /// those of the [Operation]
+    Apply {
+        lhs: Box<Operand>,
+        op: Box<Operation>,
+    },
+    /// An operand of which the result is stored under `name`
+    Named { name: String, operand: Box<Operand> },
+}
 
-        reader.begin_object().await?;
+/// An [Operand] as it occurs in the input, before any of its values are
+/// validated. Work that is read fully into memory is deserialized into this
+/// first, so that validating it fails with a [StrompyError] rather than a
+/// message wrapped in an error of the deserializer.
+#[derive(serde::Deserialize)]
+struct RawOperand {
+    #[serde(rename = "ref")]
+    name: Option<String>,
+    d: Option<Vec<f64>>,
+    n: Option<usize>,
+    order: Option<String>,
+    code: Option<String>,
+    lhs: Option<Box<RawOperand>>,
+    rhs: Option<Box<RawOperand>>,
+    #[serde(flatten)]
+    params: RawOpParams,
+    #[serde(rename = "as")]
+    alias: Option<String>,
+}
 
-        // Read op code
-        let "code" = reader.next_name().await? else {
-            return Err(StrompyError::Json(
-                r#"Unexpected key encountered, expected "code""#,
-            ));
+impl TryFrom<RawOperand> for Operand {
+    type Error = StrompyError;
+
+    fn try_from(raw: RawOperand) -> StrompyResult<Self> {
+        let nested = |operand: Box<RawOperand>| Self::try_from(*operand).map(Box::new);
+        let fields = OperandFields {
+            name: raw.name,
+            d: convert(raw.d, "d", |d| Storage::from_slice(&d))?,
+            n: raw.n,
+            order: convert(raw.order, "order", Order::try_from)?,
+            code: raw.code,
+            lhs: convert(raw.lhs, "lhs", nested)?,
+            rhs: convert(raw.rhs, "rhs", nested)?,
+            params: OpParams::try_from(raw.params)?,
+            alias: raw.alias,
         };
-        let code = reader.next_str().await?;
+        Self::try_from(fields)
+    }
+}
 
-        // Depending on op code, read further data
-        let op = match code {
-            "dot" => Self::Dot {
-                rhs: read_rhs(reader).await?,
-            },
-            _ => return Err(StrompyError::Json("Unexpected Operation code")),
+/// The fields of an [Operand], of which the values have been read,
+/// but which have not been checked to form a valid operand yet
+#[derive(Default)]
+struct OperandFields {
+    name: Option<String>,
+    d: Option<Storage<f64, MATRIX_CAPACITY>>,
+    n: Option<usize>,
+    order: Option<Order>,
+    code: Option<String>,
+    lhs: Option<Box<Operand>>,
+    rhs: Option<Box<Operand>>,
+    params: OpParams,
+    alias: Option<String>,
+}
+
+impl TryFrom<OperandFields> for Operand {
+    type Error = StrompyError;
+
+    fn try_from(fields: OperandFields) -> StrompyResult<Self> {
+        let OperandFields {
+            name,
+            d,
+            n,
+            order,
+            code,
+            lhs,
+            rhs,
+            params,
+            alias,
+        } = raw;
+
+        // Each form of operand allows only its own keys, apart from `as`
+        let reject = |key: &'static str, others: &[(&'static str, bool)]| {
+            match others.iter().find(|(_, present)| *present) {
+                Some(&(other, _)) => Err(StrompyError::ConflictingKeys(key, other)),
+                None => Ok(()),
+            }
         };
 
-        reader.end_object().await?;
+        let operand = match (name, code) {
+            (Some(_), Some(_)) => return Err(StrompyError::ConflictingKeys("ref", "code")),
+            (Some(name), None) => {
+                let operands = [
+                    ("d", d.is_some()),
+                    ("n", n.is_some()),
+                    ("order", order.is_some()),
+                    ("lhs", lhs.is_some()),
+                    ("rhs", rhs.is_some()),
+                ];
+                reject("ref", &[&operands[..], &params.present()[..]].concat())?;
+                Self::Ref(name)
+            }
+            (None, Some(code)) => {
+                let matrix = [
+                    ("d", d.is_some()),
+                    ("n", n.is_some()),
+                    ("order", order.is_some()),
+                ];
+                reject("code", &matrix)?;
+                let op = Operation::from_fields(&code, rhs.map(|rhs| *rhs), params)?;
+                Self::Apply {
+                    lhs: required(lhs, "lhs")?,
+                    op: Box::new(op),
+                }
+            }
+            (None, None) => {
+                if lhs.is_some() || rhs.is_some() || !params.is_empty() {
+                    return Err(StrompyError::MissingKey("code"));
+                }
+                let (d, n) = (required(d, "d")?, required(n, "n")?);
+                Self::Inline(MatrixBuf::with_order(d, n, order.unwrap_or_default())?)
+            }
+        };
 
-        Ok(op)
+        Ok(match alias {
+            Some(name) => Self::Named {
+                name,
+                operand: Box::new(operand),
+            },
+            None => operand,
+        })
     }
 }
 
-/// A single piece of work
-#[derive(serde::Deserialize, Debug)]
-pub struct PieceOfWork {
-    lhs: MatrixBuf,
-    op: HeaplessVec<Operation, 5>,
-}
+impl Operand {
+    /// The name of the result this operand refers to, if any
+    fn ref_name(&self) -> Option<&str> {
+        match self {
+            Operand::Ref(name) => Some(name),
+            _ => None,
+        }
+    }
 
-impl PieceOfWork {
-    /// Execute a single [PieceOfWork] that
-    /// has already been read fully into memory.
-    pub fn exec(self) -> MatrixBuf {
-        let res = self
-            .op
-            .into_iter()
-            .fold(self.lhs, |rhs: MatrixBuf, op| op.eval(rhs));
+    /// Call `f` on this operand and on every operand nested in it
+    fn walk<'a>(&'a self, f: &mut impl FnMut(&'a Operand)) {
+        f(self);
+        match self {
+            Operand::Inline(_) | Operand::Ref(_) => {}
+            Operand::Apply { lhs, op } => {
+                lhs.walk(f);
+                if let Some(rhs) = op.rhs() {
+                    rhs.walk(f);
+                }
+            }
+            Operand::Named { operand, .. } => operand.walk(f),
+        }
+    }
 
-        res
+    /// The matrix this operand stands for. Only matrix data and references
+    /// can be looked up this way, anything else has to be evaluated first.
+    fn matrix<'a>(&'a self, scope: &'a Scope<'_>) -> StrompyResult<&'a MatrixBuf> {
+        match self {
+            Operand::Inline(m) => Ok(m),
+            Operand::Ref(name) => scope.get(name),
+            Operand::Apply { .. } | Operand::Named { .. } => {
+                unreachable!("operand is evaluated before it is used")
+            }
+        }
     }
 
-    /// Read and execute a single [PieceOfWork]
-    pub async fn exec_streamingly<R: AsyncRead + Unpin>(
-        reader: &mut JsonStreamReader<R>,
-    ) -> StrompyResult<MatrixBuf> {
-        reader.begin_object().await?;
+    /// Whether this operand needs to be evaluated before it can be used
+    fn is_expr(&self) -> bool {
+        matches!(self, Operand::Apply { .. } | Operand::Named { .. })
+    }
 
-        // First, we need the `lhs` object
-        let "lhs" = reader.next_name().await? else {
-            return Err(StrompyError::Json(
-                r#"Unexpected key encountered, expected "lhs""#,
-            ));
-        };
-        let lhs: MatrixBuf = MatrixBuf::deserialize(reader).await?;
+    /// Evaluate the operand, looking up references in `scope` and storing
+    /// any named results in it. Referenced results are copied.
+    fn eval(self, scope: &mut Scope<'_>) -> StrompyResult<MatrixBuf> {
+        match self {
+            Operand::Inline(m) => Ok(m),
+            Operand::Ref(name) => scope.get(&name).cloned(),
+            Operand::Apply { lhs, op } => {
+                let lhs = lhs
+                    .eval(scope)
+                    .map_err(|e| e.within(PathPiece::Key("lhs".to_owned())))?;
+                op.eval(lhs, scope)?.into_matrix()
+            }
+            Operand::Named { name, operand } => {
+                let res = operand.eval(scope)?;
+                scope.insert(name, res.clone());
+                Ok(res)
+            }
+        }
+    }
 
-        // Then, we read the `op` array element-by-element
-        let "op" = reader.next_name().await? else {
-            return Err(StrompyError::Json(
-                r#"Unexpected key encountered, expected "op""#,
-            ));
-        };
+    /// Read an operand, evaluating any operation in it as soon as its operands
+    /// have been read. Returns either the resulting matrix, or a reference,
+    /// which is looked up only once it is used.
+    ///
+    /// Whatever the order of the keys in the input, the `lhs` of an operation
+    /// is evaluated before its `rhs`, like it is in [Operand::eval]. So names
+    /// defined in the `rhs` cannot be referred to from the `lhs`, and a `rhs`
+    /// that comes before the `lhs` is read as is, to be evaluated afterwards.
+    pub async fn eval_streamingly<R: AsyncRead + Unpin>(
+        reader: &mut JsonStreamReader<R>,
+        scope: &mut Scope<'_>,
+    ) -> StrompyResult<Self> {
+        Self::read_streamingly(reader, Some(scope)).await
+    }
 
-        reader.begin_array().await?;
+    /// Read an operand, evaluating it as it is read if there is a `scope`
+    /// to do so in, see [Operand::eval_streamingly], or reading it as is otherwise
+    async fn read_streamingly<R: AsyncRead + Unpin>(
+        reader: &mut JsonStreamReader<R>,
+        mut scope: Option<&mut Scope<'_>>,
+    ) -> StrompyResult<Self> {
+        // Remember where the operand is, so that we can
+        // report its location should evaluating it fail
+        let location = Location::of_reader(reader);
+        // Every operand nested in this one is read by a recursive call,
+        // so the depth of the input has to be limited
+        if location.path.len() >= MAX_DEPTH {
+            let max_depth = MAX_DEPTH;
+            return Err(StrompyError::TooDeep { max_depth }.at(location));
+        }
+        reader.begin_object().await?;
 
-        // We execute operations as they come in
-        let mut res = lhs;
+        let mut raw = OperandFields::default();
         while reader.has_next().await? {
-            let op: Operation = Operation::deserialize(reader).await?;
-            res = op.eval(res);
+            match reader.next_name().await? {
+                "ref" => set_once(&mut raw.name, "ref", reader.next_str().await?.to_owned())?,
+                "d" => set_once(&mut raw.d, "d", MatrixBuf::deserialize_data(reader).await?)?,
+                "n" => set_once(&mut raw.n, "n", reader.next_number().await??)?,
+                "order" => {
+                    let value = Order::try_from(reader.next_str().await?.to_owned())?;
+                    set_once(&mut raw.order, "order", value)?
+                }
+                "code" => set_once(&mut raw.code, "code", reader.next_str().await?.to_owned())?,
+                "as" => set_once(&mut raw.alias, "as", reader.next_str().await?.to_owned())?,
+                "lhs" => {
+                    let location = Location::of_reader(reader);
+                    let lhs = Self::read_nested(reader, scope.as_deref_mut()).await?;
+                    // Look up a reference right away, before the `rhs` can define its name
+                    let lhs = match scope.as_deref_mut() {
+                        Some(scope) => {
+                            Box::new(Self::Inline(lhs.eval(scope).map_err(|e| e.at(location))?))
+                        }
+                        None => lhs,
+                    };
+                    set_once(&mut raw.lhs, "lhs", lhs)?
+                }
+                "rhs" => {
+                    let scope = scope.as_deref_mut().filter(|_| raw.lhs.is_some());
+                    set_once(&mut raw.rhs, "rhs", Self::read_nested(reader, scope).await?)?
+                }
+                name => match OpParams::key(name) {
+                    Some(key) => raw.params.read(key, reader).await?,
+                    None => reader.skip_value().await?,
+                },
+            }
         }
 
-        reader.end_array().await?;
-
         reader.end_object().await?;
 
-        Ok(res)
+        let operand = Self::try_from(raw).map_err(|e| e.at(location.clone()))?;
+        match scope {
+            Some(scope) if operand.is_expr() => {
+                // The `lhs` of any operation has been evaluated already, and so
+                // has the `rhs` unless it came first. What is left is evaluated
+                // in memory, and located relative to this operand.
+                let res = operand.eval(scope).map_err(|e| e.relative_to(location))?;
+                Ok(Self::Inline(res))
+            }
+            _ => Ok(operand),
+        }
+    }
+
+    /// [Operand::read_streamingly] for operands nested in other operands
+    async fn read_nested<R: AsyncRead + Unpin>(
+        reader: &mut JsonStreamReader<R>,
+        scope: Option<&mut Scope<'_>>,
+    ) -> StrompyResult<Box<Self>> {
+        let operand = Box::pin(Self::read_streamingly(reader, scope)).await?;
+        Ok(Box::new(operand))
     }
 }
 
-mod strompychan {
-    use std::sync::Arc;
+impl From<MatrixBuf> for Operand {
+    fn from(m: MatrixBuf) -> Self {
+        Self::Inline(m)
+    }
+}
 
-    use futures::lock::Mutex;
-    use pychan::reader::PyBytesReader;
-    use pyo3::{pyclass, pymethods, types::PyList, PyResult, Python};
-    use struson::reader::{JsonReader, JsonStreamReader};
+#[cfg(feature = "python")]
+impl std::iter::IntoIterator for MatrixBuf {
+    type Item = Py<PyList>;
 
-    use crate::{MatrixBuf, PieceOfWork, StrompyResult};
+    type IntoIter = MatrixBufIter;
 
-    struct StrompyJsonReaderInner {
-        reader: JsonStreamReader<PyBytesReader>,
-        in_array: bool,
+    fn into_iter(self) -> Self::IntoIter {
+        MatrixBufIter { buf: self, i: 0 }
     }
+}
 
-    #[pyclass]
-    #[derive(Clone)]
-    pub struct StrompyJsonReader {
-        inner: Arc<Mutex<StrompyJsonReaderInner>>,
-    }
+#[cfg(feature = "python")]
+pub struct MatrixBufIter {
+    buf: MatrixBuf,
+    i: usize,
+}
 
-    impl StrompyJsonReader {
-        pub fn new(reader: PyBytesReader) -> Self {
-            let reader = JsonStreamReader::new(reader);
-            let inner = StrompyJsonReaderInner {
-                reader,
-                in_array: false,
-            };
+#[cfg(feature = "python")]
+impl std::iter::Iterator for MatrixBufIter {
+    type Item = Py<PyList>;
 
-            Self {
-                inner: Arc::new(Mutex::new(inner)),
-            }
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.i >= self.buf.shape().rows {
+            None
+        } else {
+            let view = self.buf.view();
+            let row = view.row(self.i);
+            let items = row.iter().copied();
+            let item: Py<PyList> = Python::with_gil(|py| PyList::new_bound(py, items).unbind());
+            self.i += 1;
+            Some(item)
         }
+    }
 
-        pub async fn next(&mut self) -> StrompyResult<Option<MatrixBuf>> {
-            let mut inner = self.inner.lock().await;
-            if !inner.in_array {
-                inner.reader.begin_array().await.unwrap();
-                inner.in_array = true;
-            }
-            if inner.reader.has_next().await? {
-                let next = PieceOfWork::exec_streamingly(&mut inner.reader).await?;
-                Ok(Some(next))
-            } else {
-                Ok(None)
-            }
-        }
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        (self.len(), Some(self.len()))
     }
+}
 
-    #[pymethods]
-    impl StrompyJsonReader {
-        #[pyo3(name = "next")]
-        async fn next_py(&mut self) -> PyResult<Option<pyo3::Py<PyList>>> {
-            let next = self
-                .next()
-                .await?
-                .map(|m| Python::with_gil(|py| PyList::new_bound(py, m).unbind()));
-            Ok(next)
-        }
+#[cfg(feature = "python")]
+impl ExactSizeIterator for MatrixBufIter {
+    fn len(&self) -> usize {
+        self.buf.shape().rows - self.i
     }
 }
 
-mod py {
-    use pychan::py_bytes::PyBytesSender;
+/// An operation that can be performed on a Matrix
+#[derive(serde::Deserialize, Debug, Clone)]
+#[serde(try_from = "RawOperation")]
+pub enum Operation {
+    /// Perform the dot product of some matrix with `rhs`
+    Dot { rhs: Operand },
+    /// Perform the matrix product of some matrix with `rhs`
+    MatMul { rhs: Operand },
+    /// Add `rhs` to some matrix element-wise
+    Add { rhs: Operand },
+    /// Subtract `rhs` from some matrix element-wise
+    Sub { rhs: Operand },
+    /// Multiply each element of some matrix by `factor`
+    Scale { factor: f64 },
+    /// Transpose some matrix
+    Transpose,
+    /// Invert some square matrix
+    Inverse,
+    /// Compute the determinant of some square matrix, as a 1x1 matrix
+    Determinant,
+    /// Solve `A·x = rhs` for `x`, where `A` is some square matrix
+    Solve { rhs: Operand },
+    /// Compute the lower-triangular `L` with `A = L·Lᵀ` of some symmetric
+    /// positive-definite matrix `A`. Only the lower triangle of `A` is used.
+    Cholesky,
+    /// Decompose some matrix `A` into factors `p`, `l` and `u` with `P·A = L·U`,
+    /// where `P` is a permutation matrix, `L` is lower-triangular with
+    /// a unit diagonal and `U` is upper-triangular
+    Lu,
+    /// Decompose some matrix `A` into factors `q` and `r` with `A = Q·R`,
+    /// where `Q` has orthonormal columns and `R` is upper-triangular
+    Qr,
+    /// Decompose some matrix `A` into factors `u`, `s` and `v_t` with
+    /// `A = U·diag(s)·Vᵀ`, where `s` is a column of singular values
+    Svd,
+    /// Take the absolute value of each element of some matrix
+    Abs,
+    /// Take the exponential of each element of some matrix
+    Exp,
+    /// Take the natural logarithm of each element of some matrix,
+    /// which is NaN for negative elements
+    Log,
+    /// Take the square root of each element of some matrix,
+    /// which is NaN for negative elements
+    Sqrt,
+    /// Raise each element of some matrix to the power `exponent`
+    Pow { exponent: f64 },
+    /// Limit each element of some matrix to lie between `min` and `max`,
+    /// either of which can be left out
+    Clamp { min: Option<f64>, max: Option<f64> },
+    /// Sum the elements of some matrix along `axis`
+    Sum { axis: Axis },
+    /// Average the elements of some matrix along `axis`
+    Mean { axis: Axis },
+    /// Take the smallest element of some matrix along `axis`
+    Min { axis: Axis },
+    /// Take the largest element of some matrix along `axis`
+    Max { axis: Axis },
+    /// Compute the norm `kind` of some matrix along `axis`
+    Norm { axis: Axis, kind: NormKind },
+    /// Select the rows in the range `rows` of the columns in the range `cols`
+    /// of some matrix, either of which can be left out to select all of them
+    Slice { rows: SliceRange, cols: SliceRange },
+    /// Give the elements of some matrix the shape `shape`, keeping them in
+    /// the order in which they are stored, which is column by column
+    Reshape { shape: Shape },
+    /// Append the columns of `rhs` to those of some matrix
+    HStack { rhs: Operand },
+    /// Append the rows of `rhs` to those of some matrix
+    VStack { rhs: Operand },
+    /// Turn some row or column into a square matrix with its elements on the
+    /// diagonal, or take the diagonal of any other matrix as a column
+    Diag,
+}
 
-    use futures::SinkExt;
-    use pyo3::{prelude::*, types::PyBytes};
+/// An [Operation] as it occurs in the input, before any of its values
+/// are validated, see [RawOperand]
+#[derive(serde::Deserialize)]
+struct RawOperation {
+    code: Option<String>,
+    rhs: Option<RawOperand>,
+    #[serde(flatten)]
+    params: RawOpParams,
+}
 
-    use crate::{strompychan::StrompyJsonReader, MatrixBuf, PieceOfWork};
+impl TryFrom<RawOperation> for Operation {
+    type Error = StrompyError;
 
-    impl From<MatrixBuf> for Vec<Vec<f64>> {
-        fn from(MatrixBuf { d, n }: MatrixBuf) -> Self {
-            d.chunks_exact(n).into_iter().map(|c| c.to_vec()).collect()
-        }
+    fn try_from(RawOperation { code, rhs, params }: RawOperation) -> StrompyResult<Self> {
+        let rhs = convert(rhs, "rhs", Operand::try_from)?;
+        let params = OpParams::try_from(params)?;
+        Self::from_fields(&required(code, "code")?, rhs, params)
     }
+}
 
-    #[pyfunction]
-    fn exec(json_bytes: &[u8]) -> PyResult<Vec<Vec<Vec<f64>>>> {
-        let work: Vec<PieceOfWork> = serde_json::from_reader(json_bytes).unwrap();
+/// [OpParams] as they occur in the input, before their values are validated
+#[derive(serde::Deserialize)]
+struct RawOpParams {
+    factor: Option<f64>,
+    exponent: Option<f64>,
+    min: Option<f64>,
+    max: Option<f64>,
+    axis: Option<String>,
+    kind: Option<String>,
+    rows: Option<SliceRange>,
+    cols: Option<SliceRange>,
+    shape: Option<Shape>,
+}
 
-        Ok(work.into_iter().map(|p| p.exec().into()).collect())
+impl TryFrom<RawOpParams> for OpParams {
+    type Error = StrompyError;
+
+    fn try_from(raw: RawOpParams) -> StrompyResult<Self> {
+        Ok(Self {
+            factor: raw.factor,
+            exponent: raw.exponent,
+            min: raw.min,
+            max: raw.max,
+            axis: convert(raw.axis, "axis", Axis::try_from)?,
+            kind: convert(raw.kind, "kind", NormKind::try_from)?,
+            rows: raw.rows,
+            cols: raw.cols,
+            shape: raw.shape,
+        })
     }
+}
 
-    #[pyfunction]
-    fn channel() -> (PyBytesSender, StrompyJsonReader) {
-        let (tx, rx) = pychan::py_bytes::channel(16);
-        let reader = rx.into_reader();
-        let reader = StrompyJsonReader::new(reader);
+/// The parameters of an [Operation] other than its code and right-hand side,
+/// as read from the input. Which of them are used depends on the code.
+#[derive(Debug, Default, Clone)]
+struct OpParams {
+    factor: Option<f64>,
+    exponent: Option<f64>,
+    min: Option<f64>,
+    max: Option<f64>,
+    axis: Option<Axis>,
+    kind: Option<NormKind>,
+    rows: Option<SliceRange>,
+    cols: Option<SliceRange>,
+    shape: Option<Shape>,
+}
 
-        (tx, reader)
+impl OpParams {
+    /// The keys of all parameters
+    const KEYS: [&'static str; 9] = [
+        "factor", "exponent", "min", "max", "axis", "kind", "rows", "cols", "shape",
+    ];
+
+    /// Each key of [OpParams::KEYS] along with whether it was given
+    fn present(&self) -> [(&'static str, bool); 9] {
+        [
+            ("factor", self.factor.is_some()),
+            ("exponent", self.exponent.is_some()),
+            ("min", self.min.is_some()),
+            ("max", self.max.is_some()),
+            ("axis", self.axis.is_some()),
+            ("kind", self.kind.is_some()),
+            ("rows", self.rows.is_some()),
+            ("cols", self.cols.is_some()),
+            ("shape", self.shape.is_some()),
+        ]
     }
 
+    /// Whether no parameter was given at all
+    fn is_empty(&self) -> bool {
+        self.present().iter().all(|&(_, present)| !present)
+    }
+
+    /// Read the value of the parameter `key`, one of [OpParams::KEYS]
+    async fn read<R: AsyncRead + Unpin>(
+        &mut self,
+        key: &'static str,
+        reader: &mut JsonStreamReader<R>,
+    ) -> StrompyResult<()> {
+        match key {
+            "factor" => set_once(&mut self.factor, key, reader.next_number().await??),
+            "exponent" => set_once(&mut self.exponent, key, reader.next_number().await??),
+            "min" => set_once(&mut self.min, key, reader.next_number().await??),
+            "max" => set_once(&mut self.max, key, reader.next_number().await??),
+            "axis" => {
+                let axis = Axis::try_from(reader.next_str().await?.to_owned())?;
+                set_once(&mut self.axis, key, axis)
+            }
+            "kind" => {
+                let kind = NormKind::try_from(reader.next_str().await?.to_owned())?;
+                set_once(&mut self.kind, key, kind)
+            }
+            "rows" => set_once(&mut self.rows, key, SliceRange::deserialize(reader).await?),
+            "cols" => set_once(&mut self.cols, key, SliceRange::deserialize(reader).await?),
+            "shape" => set_once(&mut self.shape, key, Shape::deserialize(reader).await?),
+            key => unreachable!("{key} is not a parameter"),
+        }
+    }
+
+    /// The key in [OpParams::KEYS] equal to `name`, if any
+    fn key(name: &str) -> Option<&'static str> {
+        Self::KEYS.iter().copied().find(|&key| key == name)
+    }
+}
+
+impl Operation {
+    /// The code identifying this operation in the input
+    fn code(&self) -> &'static str {
+        match self {
+            Operation::Dot { .. } => "dot",
+            Operation::MatMul { .. } => "matmul",
+            Operation::Add { .. } => "add",
+            Operation::Sub { .. } => "sub",
+            Operation::Scale { .. } => "scale",
+            Operation::Transpose => "transpose",
+            Operation::Inverse => "inverse",
+            Operation::Determinant => "determinant",
+            Operation::Solve { .. } => "solve",
+            Operation::Cholesky => "cholesky",
+            Operation::Lu => "lu",
+            Operation::Qr => "qr",
+            Operation::Svd => "svd",
+            Operation::Abs => "abs",
+            Operation::Exp => "exp",
+            Operation::Log => "log",
+            Operation::Sqrt => "sqrt",
+            Operation::Pow { .. } => "pow",
+            Operation::Clamp { .. } => "clamp",
+            Operation::Sum { .. } => "sum",
+            Operation::Mean { .. } => "mean",
+            Operation::Min { .. } => "min",
+            Operation::Max { .. } => "max",
+            Operation::Norm { .. } => "norm",
+            Operation::Slice { .. } => "slice",
+            Operation::Reshape { .. } => "reshape",
+            Operation::HStack { .. } => "hstack",
+            Operation::VStack { .. } => "vstack",
+            Operation::Diag => "diag",
+        }
+    }
+
+    /// The names of the matrices this operation results in
+    /// if it is a decomposition, see [Factors]
+    fn factor_names(&self) -> Option<&'static [&'static str]> {
+        match self {
+            Operation::Lu => Some(&["p", "l", "u"]),
+            Operation::Qr => Some(&["q", "r"]),
+            Operation::Svd => Some(&["u", "s", "v_t"]),
+            _ => None,
+        }
+    }
+
+    /// The name and shape of every matrix this operation results in if it is
+    /// a decomposition of a matrix of shape `lhs`, in the order of
+    /// [Operation::factor_names]
+    fn factor_shapes(&self, lhs: Shape) -> Option<Vec<(&'static str, Shape)>> {
+        let shape = |rows, cols| Shape { rows, cols };
+        let (m, n) = (lhs.rows, lhs.cols);
+        let k = m.min(n);
+        let shapes = match self {
+            Operation::Lu => vec![shape(m, m), shape(m, k), shape(k, n)],
+            Operation::Qr => vec![shape(m, k), shape(k, n)],
+            Operation::Svd => vec![shape(m, k), shape(k, 1), shape(k, n)],
+            _ => return None,
+        };
+        let names = self.factor_names()?;
+        Some(names.iter().copied().zip(shapes).collect())
+    }
+
+    /// The right-hand side operand of this operation, if it has one
+    fn rhs(&self) -> Option<&Operand> {
+        match self {
+            Operation::Dot { rhs }
+            | Operation::MatMul { rhs }
+            | Operation::Add { rhs }
+            | Operation::Sub { rhs }
+            | Operation::Solve { rhs }
+            | Operation::HStack { rhs }
+            | Operation::VStack { rhs } => Some(rhs),
+            Operation::Scale { .. }
+            | Operation::Transpose
+            | Operation::Inverse
+            | Operation::Determinant
+            | Operation::Cholesky
+            | Operation::Lu
+            | Operation::Qr
+            | Operation::Svd
+            | Operation::Abs
+            | Operation::Exp
+            | Operation::Log
+            | Operation::Sqrt
+            | Operation::Pow { .. }
+            | Operation::Clamp { .. }
+            | Operation::Sum { .. }
+            | Operation::Mean { .. }
+            | Operation::Min { .. }
+            | Operation::Max { .. }
+            | Operation::Norm { .. }
+            | Operation::Slice { .. }
+            | Operation::Reshape { .. }
+            | Operation::Diag => None,
+        }
+    }
+
+    /// Mutable counterpart of [Operation::rhs]
+    fn rhs_mut(&mut self) -> Option<&mut Operand> {
+        match self {
+            Operation::Dot { rhs }
+            | Operation::MatMul { rhs }
+            | Operation::Add { rhs }
+            | Operation::Sub { rhs }
+            | Operation::Solve { rhs }
+            | Operation::HStack { rhs }
+            | Operation::VStack { rhs } => Some(rhs),
+            Operation::Scale { .. }
+            | Operation::Transpose
+            | Operation::Inverse
+            | Operation::Determinant
+            | Operation::Cholesky
+            | Operation::Lu
+            | Operation::Qr
+            | Operation::Svd
+            | Operation::Abs
+            | Operation::Exp
+            | Operation::Log
+            | Operation::Sqrt
+            | Operation::Pow { .. }
+            | Operation::Clamp { .. }
+            | Operation::Sum { .. }
+            | Operation::Mean { .. }
+            | Operation::Min { .. }
+            | Operation::Max { .. }
+            | Operation::Norm { .. }
+            | Operation::Slice { .. }
+            | Operation::Reshape { .. }
+            | Operation::Diag => None,
+        }
+    }
+
+    /// Create an [Operation] from its code and the fields read from the input
+    fn from_fields(code: &str, rhs: Option<Operand>, params: OpParams) -> StrompyResult<Self> {
+        let OpParams {
+            factor,
+            exponent,
+            min,
+            max,
+            axis,
+            kind,
+            rows,
+            cols,
+            shape,
+        } = params;
+        let op = match code {
+            "dot" => Self::Dot {
+                rhs: required(rhs, "rhs")?,
+            },
+            "matmul" => Self::MatMul {
+                rhs: required(rhs, "rhs")?,
+            },
+            "add" => Self::Add {
+                rhs: required(rhs, "rhs")?,
+            },
+            "sub" => Self::Sub {
+                rhs: required(rhs, "rhs")?,
+            },
+            "scale" => Self::Scale {
+                factor: required(factor, "factor")?,
+            },
+            "transpose" => Self::Transpose,
+            "inverse" => Self::Inverse,
+            "determinant" => Self::Determinant,
+            "solve" => Self::Solve {
+                rhs: required(rhs, "rhs")?,
+            },
+            "cholesky" => Self::Cholesky,
+            "lu" => Self::Lu,
+            "qr" => Self::Qr,
+            "svd" => Self::Svd,
+            "abs" => Self::Abs,
+            "exp" => Self::Exp,
+            "log" => Self::Log,
+            "sqrt" => Self::Sqrt,
+            "pow" => Self::Pow {
+                exponent: required(exponent, "exponent")?,
+            },
+            "clamp" => Self::Clamp { min, max },
+            "sum" => Self::Sum {
+                axis: axis.unwrap_or_default(),
+            },
+            "mean" => Self::Mean {
+                axis: axis.unwrap_or_default(),
+            },
+            "min" => Self::Min {
+                axis: axis.unwrap_or_default(),
+            },
+            "max" => Self::Max {
+                axis: axis.unwrap_or_default(),
+            },
+            "norm" => Self::Norm {
+                axis: axis.unwrap_or_default(),
+                kind: kind.unwrap_or_default(),
+            },
+            "slice" => Self::Slice {
+                rows: rows.unwrap_or_default(),
+                cols: cols.unwrap_or_default(),
+            },
+            "reshape" => Self::Reshape {
+                shape: required(shape, "shape")?,
+            },
+            "hstack" => Self::HStack {
+                rhs: required(rhs, "rhs")?,
+            },
+            "vstack" => Self::VStack {
+                rhs: required(rhs, "rhs")?,
+            },
+            "diag" => Self::Diag,
+            code => return Err(StrompyError::UnknownOperation(code.to_owned())),
+        };
+
+        Ok(op)
+    }
+
+    /// Check that the operation can be applied to a matrix of shape `lhs`,
+    /// returning the shape of the result. The shape of the right-hand side,
+    /// if any, is given by `rhs_shape`. Decompositions result in several
+    /// matrices, of which [Operation::factor_shapes] gives the shapes, so for
+    /// those, this returns `lhs`.
+    fn check(
+        &self,
+        lhs: Shape,
+        rhs_shape: impl Fn(&Operand) -> StrompyResult<Shape>,
+    ) -> StrompyResult<Shape> {
+        let mismatch = |rhs: Shape| StrompyError::ShapeMismatch {
+            op: self.code(),
+            lhs,
+            rhs,
+        };
+        let overflow = || StrompyError::ShapeOverflow { op: self.code() };
+        let square = || {
+            if lhs.rows != lhs.cols {
+                return Err(StrompyError::NotSquare {
+                    op: self.code(),
+                    shape: lhs,
+                });
+            }
+            Ok(lhs)
+        };
+
+        let shape = match self {
+            Operation::Dot { rhs } => {
+                let rhs = rhs_shape(rhs)?;
+                if lhs != rhs {
+                    return Err(mismatch(rhs));
+                }
+                Ok(Shape { rows: 1, cols: 1 })
+            }
+            Operation::MatMul { rhs } => {
+                let rhs = rhs_shape(rhs)?;
+                if lhs.cols != rhs.rows {
+                    return Err(mismatch(rhs));
+                }
+                Ok(Shape {
+                    rows: lhs.rows,
+                    cols: rhs.cols,
+                })
+            }
+            Operation::Add { rhs } | Operation::Sub { rhs } => {
+                let rhs = rhs_shape(rhs)?;
+                if lhs != rhs {
+                    return Err(mismatch(rhs));
+                }
+                Ok(lhs)
+            }
+            Operation::Scale { .. } => Ok(lhs),
+            Operation::Transpose => Ok(Shape {
+                rows: lhs.cols,
+                cols: lhs.rows,
+            }),
+            Operation::Inverse | Operation::Cholesky => square(),
+            Operation::Determinant => square().map(|_| Shape { rows: 1, cols: 1 }),
+            Operation::Solve { rhs } => {
+                square()?;
+                let rhs = rhs_shape(rhs)?;
+                if lhs.rows != rhs.rows {
+                    return Err(mismatch(rhs));
+                }
+                Ok(rhs)
+            }
+            Operation::Lu | Operation::Qr | Operation::Svd => Ok(lhs),
+            Operation::Abs
+            | Operation::Exp
+            | Operation::Log
+            | Operation::Sqrt
+            | Operation::Pow { .. }
+            | Operation::Clamp { .. } => Ok(lhs),
+            Operation::Sum { axis }
+            | Operation::Mean { axis }
+            | Operation::Min { axis }
+            | Operation::Max { axis }
+            | Operation::Norm { axis, .. } => Ok(axis.reduced(lhs)),
+            Operation::Slice { rows, cols } => Ok(Shape {
+                rows: rows.indices("rows", lhs.rows)?.len(),
+                cols: cols.indices("cols", lhs.cols)?.len(),
+            }),
+            Operation::Reshape { shape } => {
+                // A shape holding more elements than fit in a usize does not
+                // match any matrix, so overflow is not an error by itself
+                match (lhs.size(), shape.size()) {
+                    (Some(from), Some(to)) if from == to => Ok(*shape),
+                    _ => Err(StrompyError::InvalidReshape {
+                        from: lhs,
+                        to: *shape,
+                    }),
+                }
+            }
+            Operation::HStack { rhs } => {
+                let rhs = rhs_shape(rhs)?;
+                if lhs.rows != rhs.rows {
+                    return Err(mismatch(rhs));
+                }
+                Ok(Shape {
+                    rows: lhs.rows,
+                    cols: lhs.cols.checked_add(rhs.cols).ok_or_else(overflow)?,
+                })
+            }
+            Operation::VStack { rhs } => {
+                let rhs = rhs_shape(rhs)?;
+                if lhs.cols != rhs.cols {
+                    return Err(mismatch(rhs));
+                }
+                Ok(Shape {
+                    rows: lhs.rows.checked_add(rhs.rows).ok_or_else(overflow)?,
+                    cols: lhs.cols,
+                })
+            }
+            Operation::Diag => Ok(lhs.diag()),
+        }?;
+
+        // Validation derives shapes without creating any matrices,
+        // so nothing else keeps them from growing without bound
+        shape.size().ok_or_else(overflow)?;
+        Ok(shape)
+    }
+
+    /// Evaluate the operation, given a [MatrixBuf]. References are
+    /// looked up in `scope`, and named results are stored in it.
+    fn eval(mut self, lhs: MatrixBuf, scope: &mut Scope<'_>) -> StrompyResult<WorkResult> {
+        if let Some(rhs) = self.rhs_mut().filter(|rhs| rhs.is_expr()) {
+            let expr = core::mem::replace(rhs, Operand::Ref(String::new()));
+            let res = expr
+                .eval(scope)
+                .map_err(|e| e.within(PathPiece::Key("rhs".to_owned())))?;
+            *rhs = Operand::Inline(res);
+        }
+        let scope = &*scope;
+
+        // The right-hand side has been evaluated already,
+        // so it is either matrix data or a reference
+        self.check(lhs.shape(), |rhs| rhs.matrix(scope).map(MatrixBuf::shape))?;
+
+        let res = match self {
+            Operation::Dot { rhs } => {
+                let dot = lhs.view().dot(&rhs.matrix(scope)?.view());
+                MatrixBuf {
+                    d: Storage::from_slice(&[dot])?,
+                    n: 1,
+                }
+            }
+            Operation::MatMul { rhs } => {
+                let (a, b) = (lhs.view(), rhs.matrix(scope)?.view());
+                let mut res = MatrixBuf::zeros(a.nrows(), b.ncols())?;
+                res.view_mut().gemm(1.0, &a, &b, 0.0);
+                res
+            }
+            Operation::Add { rhs } => {
+                let mut res = lhs;
+                let mut view = res.view_mut();
+                view += &rhs.matrix(scope)?.view();
+                res
+            }
+            Operation::Sub { rhs } => {
+                let mut res = lhs;
+                let mut view = res.view_mut();
+                view -= &rhs.matrix(scope)?.view();
+                res
+            }
+            Operation::Scale { factor } => {
+                let mut res = lhs;
+                res.d.iter_mut().for_each(|x| *x *= factor);
+                res
+            }
+            Operation::Transpose => lhs.transpose()?,
+            Operation::Inverse => lhs.inverse()?,
+            Operation::Determinant => lhs.determinant()?,
+            Operation::Solve { rhs } => lhs.solve(rhs.matrix(scope)?)?,
+            Operation::Cholesky => lhs.cholesky()?,
+            Operation::Lu => return lhs.lu().map(WorkResult::Factors),
+            Operation::Qr => return lhs.qr().map(WorkResult::Factors),
+            Operation::Svd => return lhs.svd().map(WorkResult::Factors),
+            Operation::Abs => lhs.map(f64::abs),
+            Operation::Exp => lhs.map(f64::exp),
+            Operation::Log => lhs.map(f64::ln),
+            Operation::Sqrt => lhs.map(f64::sqrt),
+            Operation::Pow { exponent } => lhs.map(|x| x.powf(exponent)),
+            Operation::Clamp { min, max } => lhs.map(|x| reduce::clamp(x, min, max)),
+            Operation::Sum { axis } => lhs.reduce(Reduction::Sum, axis)?,
+            Operation::Mean { axis } => lhs.reduce(Reduction::Mean, axis)?,
+            Operation::Min { axis } => lhs.reduce(Reduction::Min, axis)?,
+            Operation::Max { axis } => lhs.reduce(Reduction::Max, axis)?,
+            Operation::Norm { axis, kind } => lhs.reduce(Reduction::Norm(kind), axis)?,
+            Operation::Slice { rows, cols } => lhs.slice(rows, cols)?,
+            Operation::Reshape { shape } => lhs.reshape(shape)?,
+            Operation::HStack { rhs } => lhs.hstack(rhs.matrix(scope)?)?,
+            Operation::VStack { rhs } => lhs.vstack(rhs.matrix(scope)?)?,
+            Operation::Diag => lhs.diag()?,
+        };
+
+        Ok(res.into())
+    }
+
+    /// Read an operation, evaluating its right-hand side as it is read if there
+    /// is a `scope` to do so in, see [Operand::eval_streamingly]
+    pub async fn deserialize<R: AsyncRead + Unpin>(
+        reader: &mut JsonStreamReader<R>,
+        mut scope: Option<&mut Scope<'_>>,
+    ) -> StrompyResult<Self> {
+        reader.begin_object().await?;
+
+        // The op code may come after the data it applies to,
+        // so we collect all known fields before interpreting them
+        let mut code: Option<String> = None;
+        let mut rhs = None;
+        let mut params = OpParams::default();
+        while reader.has_next().await? {
+            let name = reader.next_name().await?;
+            match name {
+                "code" => set_once(&mut code, "code", reader.next_str().await?.to_owned())?,
+                "rhs" => set_once(
+                    &mut rhs,
+                    "rhs",
+                    Operand::read_streamingly(reader, scope.as_deref_mut()).await?,
+                )?,
+                name => match OpParams::key(name) {
+                    Some(key) => params.read(key, reader).await?,
+                    None => reader.skip_value().await?,
+                },
+            }
+        }
+
+        reader.end_object().await?;
+
+        // Depending on op code, pick the data we need
+        Self::from_fields(&required(code, "code")?, rhs, params)
+    }
+}
+
+/// A single piece of work: an operand, with a chain of operations applied to it.
+/// Instead of `lhs` and `op`, a piece of work can also be given as `expr`,
+/// holding a single [Operand] that may be an expression of any shape.
+#[derive(serde::Deserialize, Debug, Clone)]
+#[serde(try_from = "RawPieceOfWork")]
+pub struct PieceOfWork {
+    lhs: Operand,
+    op: OpChain,
+    /// The key `lhs` was given under in the input, for locating errors
+    lhs_key: &'static str,
+    /// The name under which the result is stored for later work to refer to
+    name: Option<String>,
+}
+
+/// A [PieceOfWork] as it occurs in the input, before any of its values
+/// are validated, see [RawOperand]
+#[derive(serde::Deserialize)]
+struct RawPieceOfWork {
+    lhs: Option<RawOperand>,
+    op: Option<Vec<RawOperation>>,
+    expr: Option<RawOperand>,
+    #[serde(rename = "as")]
+    name: Option<String>,
+}
+
+impl TryFrom<RawPieceOfWork> for PieceOfWork {
+    type Error = StrompyError;
+
+    fn try_from(raw: RawPieceOfWork) -> StrompyResult<Self> {
+        let RawPieceOfWork {
+            lhs,
+            op,
+            expr,
+            name,
+        } = raw;
+
+        // Every operation is validated before it is added to the chain,
+        // so that an error in it is located by its index
+        let op_chain = |ops: Vec<RawOperation>| -> StrompyResult<OpChain> {
+            let mut chain = OpChain::new();
+            for (i, op) in ops.into_iter().enumerate() {
+                let op = Operation::try_from(op).map_err(|e| e.within(PathPiece::Index(i)))?;
+                chain.push(op)?;
+            }
+            Ok(chain)
+        };
+
+        match (expr, lhs, op) {
+            // An expression is the same as a left-hand side without operations
+            (Some(expr), None, None) => Ok(Self {
+                lhs: Operand::try_from(expr)
+                    .map_err(|e| e.within(PathPiece::Key("expr".to_owned())))?,
+                op: OpChain::new(),
+                lhs_key: "expr",
+                name,
+            }),
+            (Some(_), Some(_), _) => Err(StrompyError::ConflictingKeys("expr", "lhs")),
+            (Some(_), None, Some(_)) => Err(StrompyError::ConflictingKeys("expr", "op")),
+            (None, lhs, op) => Ok(Self {
+                lhs: required(convert(lhs, "lhs", Operand::try_from)?, "lhs")?,
+                op: required(convert(op, "op", op_chain)?, "op")?,
+                lhs_key: "lhs",
+                name,
+            }),
+        }
+    }
+}
+
+impl PieceOfWork {
+    /// Execute a single [PieceOfWork] that
+    /// has already been read fully into memory.
+    pub fn exec(self) -> StrompyResult<WorkResult> {
+        self.exec_into(&mut Scope::new())
+    }
+
+    /// Execute a single [PieceOfWork] that has already been read fully into
+    /// memory, looking up references in `scope`, and storing the result
+    /// and any named intermediate results in `scope`
+    pub fn exec_into(self, scope: &mut Scope<'_>) -> StrompyResult<WorkResult> {
+        let PieceOfWork {
+            lhs,
+            op,
+            lhs_key,
+            name,
+        } = self;
+
+        let lhs = lhs
+            .eval(scope)
+            .map_err(|e| e.within(PathPiece::Key(lhs_key.to_owned())))?;
+
+        // Only the last operation can be a decomposition,
+        // as every other operation needs a single matrix
+        let res = op
+            .into_iter()
+            .enumerate()
+            .try_fold(lhs.into(), |rhs: WorkResult, (i, op)| {
+                rhs.into_matrix()
+                    .and_then(|rhs| op.eval(rhs, scope))
+                    .map_err(|e| {
+                        e.within(PathPiece::Index(i))
+                            .within(PathPiece::Key("op".to_owned()))
+                    })
+            })?;
+
+        if let Some(name) = name {
+            res.store(&name, scope);
+        }
+        Ok(res)
+    }
+
+    /// Call `f` on every operand in this piece of work
+    fn walk<'a>(&'a self, f: &mut impl FnMut(&'a Operand)) {
+        self.lhs.walk(f);
+        for rhs in self.op.iter().filter_map(Operation::rhs) {
+            rhs.walk(f);
+        }
+    }
+
+    /// Whether this piece of work refers to any of `names`
+    fn refers_to(&self, names: &HashSet<String>) -> bool {
+        let mut refers = false;
+        self.walk(&mut |operand| {
+            refers |= operand.ref_name().is_some_and(|name| names.contains(name))
+        });
+        refers
+    }
+
+    /// The names of all results this piece of work stores
+    fn names(&self) -> Vec<String> {
+        let factors = self.op.last().and_then(Operation::factor_names);
+        let mut names: Vec<String> = match (&self.name, factors) {
+            (Some(name), Some(factors)) => factors
+                .iter()
+                .map(|part| format!("{name}.{part}"))
+                .collect(),
+            (name, _) => name.iter().cloned().collect(),
+        };
+        self.walk(&mut |operand| {
+            if let Operand::Named { name, .. } = operand {
+                names.push(name.clone());
+            }
+        });
+        names
+    }
+
+    /// Read and execute a single [PieceOfWork]
+    pub async fn exec_streamingly<R: AsyncRead + Unpin>(
+        reader: &mut JsonStreamReader<R>,
+    ) -> StrompyResult<WorkResult> {
+        Self::exec_streamingly_in(reader, &mut Scope::new()).await
+    }
+
+    /// Read and execute a single [PieceOfWork], looking up references
+    /// in `scope`, and storing the result and any named intermediate
+    /// results in `scope`. Operands are evaluated as they are read,
+    /// see [Operand::eval_streamingly], though never before `lhs` is.
+    pub async fn exec_streamingly_in<R: AsyncRead + Unpin>(
+        reader: &mut JsonStreamReader<R>,
+        scope: &mut Scope<'_>,
+    ) -> StrompyResult<WorkResult> {
+        reader.begin_object().await?;
+
+        // Once we have the `lhs` or `expr` object, `res` holds the result so far.
+        // Operations that come in before `lhs` is known are buffered, and
+        // their right-hand sides are only evaluated along with them.
+        let mut res: Option<WorkResult> = None;
+        let mut lhs_key: Option<&'static str> = None;
+        let mut pending: Storage<(Operation, Location), OP_CHAIN_CAPACITY> = Storage::new();
+        let mut has_op = false;
+        let mut name: Option<String> = None;
+        while reader.has_next().await? {
+            let key = reader.next_name().await?;
+            match key {
+                "lhs" | "expr" => {
+                    let key = if key == "lhs" { "lhs" } else { "expr" };
+                    let location = Location::of_reader(reader);
+                    let lhs = Operand::eval_streamingly(reader, scope).await?;
+                    match lhs_key.replace(key) {
+                        Some(other) if other == key => return Err(StrompyError::DuplicateKey(key)),
+                        Some(other) => return Err(StrompyError::ConflictingKeys(other, key)),
+                        None if key == "expr" && has_op => {
+                            return Err(StrompyError::ConflictingKeys("op", "expr"))
+                        }
+                        None => {}
+                    }
+                    let lhs = lhs.eval(scope).map_err(|e| e.at(location))?;
+                    let mut lhs = WorkResult::from(lhs);
+                    for (op, location) in core::mem::take(&mut pending) {
+                        lhs = lhs
+                            .into_matrix()
+                            .and_then(|lhs| op.eval(lhs, scope))
+                            .map_err(|e| e.relative_to(location))?;
+                    }
+                    res = Some(lhs);
+                }
+                "op" => {
+                    if has_op {
+                        reader.skip_value().await?;
+                        return Err(StrompyError::DuplicateKey("op"));
+                    }
+                    if lhs_key == Some("expr") {
+                        reader.skip_value().await?;
+                        return Err(StrompyError::ConflictingKeys("expr", "op"));
+                    }
+                    has_op = true;
+
+                    reader.begin_array().await?;
+
+                    // We execute operations as they come in, as long as the chain
+                    // fits within the capacity an in-memory [OpChain] would have
+                    let mut len = 0;
+                    while reader.has_next().await? {
+                        len += 1;
+                        if let Some(capacity) =
+                            OpChain::capacity().filter(|&capacity| len > capacity)
+                        {
+                            return Err(StrompyError::Capacity { capacity });
+                        }
+                        // Remember where the operation is, so that we can
+                        // report its location should evaluating it fail
+                        let location = Location::of_reader(reader);
+                        let evaluated = res.is_some().then_some(&mut *scope);
+                        let op: Operation = Operation::deserialize(reader, evaluated).await?;
+                        match res.take() {
+                            Some(lhs) => {
+                                let lhs = lhs.into_matrix().map_err(|e| e.at(location.clone()))?;
+                                res = Some(op.eval(lhs, scope).map_err(|e| e.at(location))?)
+                            }
+                            None => pending.push((op, location))?,
+                        }
+                    }
+
+                    reader.end_array().await?;
+                }
+                "as" => set_once(&mut name, "as", reader.next_str().await?.to_owned())?,
+                _ => reader.skip_value().await?,
+            }
+        }
+
+        reader.end_object().await?;
+
+        // An expression stands on its own, anything else needs operations
+        if !has_op && lhs_key != Some("expr") {
+            return Err(StrompyError::MissingKey("op"));
+        }
+
+        let res = required(res, "lhs")?;
+        if let Some(name) = name {
+            res.store(&name, scope);
+        }
+        Ok(res)
+    }
+}
+
+/// Execute a JSON array of [PieceOfWork]s that
+/// has already been read fully into memory.
+/// The pieces are executed in parallel, see [exec_all].
+pub fn exec(json_bytes: &[u8]) -> StrompyResult<Vec<WorkResult>> {
+    let mut deserializer = serde_json::Deserializer::from_slice(json_bytes);
+    let work: Vec<RawPieceOfWork> =
+        serde_path_to_error::deserialize(&mut deserializer).map_err(|e| {
+            let location = Location::of_serde(&e, json_bytes);
+            StrompyError::from(e.into_inner()).at(location)
+        })?;
+
+    exec_all(from_raw(work)?)
+}
+
+/// Validate [PieceOfWork]s that have been deserialized fully into memory,
+/// locating any error by the index of the piece of work it occurred in
+fn from_raw(work: Vec<RawPieceOfWork>) -> StrompyResult<Vec<PieceOfWork>> {
+    work.into_iter()
+        .enumerate()
+        .map(|(i, p)| PieceOfWork::try_from(p).map_err(|e| e.within(PathPiece::Index(i))))
+        .collect()
+}
+
+/// Execute [PieceOfWork]s in parallel on the current rayon thread pool,
+/// returning the results in input order. If any piece fails, the error of
+/// the first failing piece is returned, located by the index of that piece.
+///
+/// A piece of work that refers to the result of another piece of work in the
+/// same batch is only executed once that other piece of work is done.
+fn exec_all(work: Vec<PieceOfWork>) -> StrompyResult<Vec<WorkResult>> {
+    use rayon::prelude::*;
+
+    let mut scope = Scope::new();
+    let mut results = Vec::with_capacity(work.len());
+    let mut work = work.into_iter().enumerate().peekable();
+    while work.peek().is_some() {
+        // Gather the longest run of pieces that do not refer to each other's results
+        let mut batch = Vec::new();
+        let mut names = HashSet::new();
+        while let Some((i, p)) = work.next_if(|(_, p)| !p.refers_to(&names)) {
+            names.extend(p.names());
+            batch.push((i, p));
+        }
+
+        // Every piece of work stores its named results in a scope of its own,
+        // which is merged into the shared scope once the batch is done
+        let res: Vec<_> = batch
+            .into_par_iter()
+            .map(|(i, p)| {
+                let mut local = scope.child();
+                let res = p
+                    .exec_into(&mut local)
+                    .map_err(|e| e.within(PathPiece::Index(i)));
+                (local.into_results(), res)
+            })
+            .collect();
+
+        // Going through the results in order makes sure the error that
+        // is returned does not depend on how the work was scheduled
+        for (named, res) in res {
+            results.push(res?);
+            scope.extend(named);
+        }
+    }
+
+    Ok(results)
+}
+
+/// How the [PieceOfWork]s in an input stream are laid out
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum InputMode {
+    /// A single JSON array containing all [PieceOfWork]s
+    #[default]
+    Array,
+    /// Any number of top-level [PieceOfWork]s, separated by optional whitespace.
+    /// This covers both newline-delimited JSON and concatenated JSON values.
+    Values,
+}
+
+impl std::str::FromStr for InputMode {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "array" => Ok(Self::Array),
+            "ndjson" | "concatenated" => Ok(Self::Values),
+            _ => Err(format!(
+                r#"Unknown input mode "{s}", expected "array", "ndjson" or "concatenated""#
+            )),
+        }
+    }
+}
+
+/// An error in a single [PieceOfWork] of a stream, after which
+/// reading continued with the next piece of work
+#[derive(Debug)]
+pub struct ItemError {
+    /// The index of the piece of work in the stream
+    pub index: usize,
+    pub error: StrompyError,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum WorkReaderState {
+    /// Nothing has been read yet
+    Start,
+    /// Reading [PieceOfWork]s
+    Reading,
+    /// All input has been read
+    Done,
+    /// An error occurred, after which the input cannot be trusted anymore
+    Failed,
+}
+
+/// Reads [PieceOfWork]s laid out according to an [InputMode],
+/// executing them one by one
+pub struct WorkReader<R> {
+    reader: JsonStreamReader<R>,
+    mode: InputMode,
+    state: WorkReaderState,
+    /// The index of the next item
+    index: usize,
+    /// Named results of the work read so far
+    scope: Scope<'static>,
+}
+
+impl<R: AsyncRead + Unpin> WorkReader<R> {
+    /// Create a [WorkReader] that reads a single JSON array
+    pub fn new(reader: R) -> Self {
+        Self::with_mode(reader, InputMode::Array)
+    }
+
+    pub fn with_mode(reader: R, mode: InputMode) -> Self {
+        let settings = ReaderSettings {
+            allow_multiple_top_level: mode == InputMode::Values,
+            ..Default::default()
+        };
+        Self {
+            reader: JsonStreamReader::new_custom(reader, settings),
+            mode,
+            state: WorkReaderState::Start,
+            index: 0,
+            scope: Scope::new(),
+        }
+    }
+
+    /// Read and execute the next [PieceOfWork], returning `None` once
+    /// the end of the input is reached. Once an error is returned,
+    /// every subsequent call will return an error as well.
+    pub async fn next(&mut self) -> StrompyResult<Option<WorkResult>> {
+        match self.state {
+            WorkReaderState::Done => return Ok(None),
+            WorkReaderState::Failed => return Err(StrompyError::Poisoned),
+            WorkReaderState::Start | WorkReaderState::Reading => {}
+        }
+
+        let res = self
+            .read_next()
+            .await
+            .map_err(|e| e.at(Location::of_reader(&self.reader)));
+        if res.is_err() {
+            self.state = WorkReaderState::Failed;
+        }
+        res
+    }
+
+    /// Like [WorkReader::next], but an error in a single [PieceOfWork] does not
+    /// end reading. Instead, it is returned as an [ItemError], after which
+    /// the rest of the piece of work is skipped. Only errors after which the
+    /// input cannot be trusted anymore, like malformed JSON, are returned as
+    /// an error, which every subsequent call will return as well.
+    pub async fn next_item(&mut self) -> StrompyResult<Option<Result<WorkResult, ItemError>>> {
+        match self.state {
+            WorkReaderState::Done => return Ok(None),
+            WorkReaderState::Failed => return Err(StrompyError::Poisoned),
+            WorkReaderState::Start | WorkReaderState::Reading => {}
+        }
+
+        let index = self.index;
+        let error = match self.read_next().await {
+            Ok(res) => return Ok(res.map(Ok)),
+            Err(e) => e.at(Location::of_reader(&self.reader)),
+        };
+
+        // Errors before the first item, like input that is not an array,
+        // and malformed input make it impossible to find the next item
+        let recoverable = match error.kind() {
+            StrompyError::Struson(ReaderError::UnexpectedValueType { .. }) => true,
+            StrompyError::Struson(_) | StrompyError::Io(_) => false,
+            _ => true,
+        };
+        if self.state == WorkReaderState::Start || !recoverable {
+            self.state = WorkReaderState::Failed;
+            return Err(error);
+        }
+
+        if let Err(e) = self.skip_rest(&error).await {
+            self.state = WorkReaderState::Failed;
+            return Err(e.at(Location::of_reader(&self.reader)));
+        }
+        self.index += 1;
+        Ok(Some(Err(ItemError { index, error })))
+    }
+
+    /// Check all remaining work without executing it, returning every problem
+    /// found. Ends at the first error after which the input cannot be read
+    /// any further, like malformed JSON, which is returned as the last problem.
+    pub async fn validate(mut self) -> Vec<StrompyError> {
+        let mut validator = Validator::new();
+        match self.state {
+            WorkReaderState::Done => return Vec::new(),
+            WorkReaderState::Failed => return vec![StrompyError::Poisoned],
+            WorkReaderState::Start | WorkReaderState::Reading => {}
+        }
+
+        if let Err(e) = self.validate_items(&mut validator).await {
+            validator.push(e.at(Location::of_reader(&self.reader)));
+        }
+        validator.into_problems()
+    }
+
+    async fn validate_items(&mut self, validator: &mut Validator) -> StrompyResult<()> {
+        if self.state == WorkReaderState::Start && self.mode == InputMode::Array {
+            self.reader.begin_array().await?;
+        }
+        self.state = WorkReaderState::Reading;
+
+        // Items are nested in the array that holds them, if any
+        let depth = match self.mode {
+            InputMode::Array => 1,
+            InputMode::Values => 0,
+        };
+        while self.reader.has_next().await? {
+            let item = validate::read_value(&mut self.reader, depth).await?;
+            validator.piece_of_work(&item, &[PathPiece::Index(self.index)]);
+            self.index += 1;
+        }
+        if self.mode == InputMode::Array {
+            self.reader.end_array().await?;
+        }
+        self.state = WorkReaderState::Done;
+        Ok(())
+    }
+
+    /// Skip the rest of the item in which `error` occurred,
+    /// so that the reader is positioned right after it
+    async fn skip_rest(&mut self, error: &StrompyError) -> StrompyResult<()> {
+        // A value of an unexpected type is left unread. Any other error
+        // occurs in between values, which is where skipping can start.
+        if let StrompyError::Struson(ReaderError::UnexpectedValueType { .. }) = error.kind() {
+            self.reader.skip_value().await?;
+        }
+
+        let depth = match self.mode {
+            InputMode::Array => 1,
+            InputMode::Values => 0,
+        };
+        loop {
+            let path = Location::of_reader(&self.reader).path;
+            if path.len() <= depth {
+                return Ok(());
+            }
+
+            let in_object = matches!(path.last(), Some(PathPiece::Key(_)));
+            match (self.reader.has_next().await?, in_object) {
+                (true, true) => {
+                    self.reader.skip_name().await?;
+                    self.reader.skip_value().await?;
+                }
+                (true, false) => self.reader.skip_value().await?,
+                (false, true) => self.reader.end_object().await?,
+                (false, false) => self.reader.end_array().await?,
+            }
+        }
+    }
+
+    async fn read_next(&mut self) -> StrompyResult<Option<WorkResult>> {
+        if self.state == WorkReaderState::Start {
+            if self.mode == InputMode::Array {
+                self.reader.begin_array().await?;
+            }
+            self.state = WorkReaderState::Reading;
+        }
+
+        if self.reader.has_next().await? {
+            // Results named within the piece of work are only kept if it succeeds
+            let mut scope = self.scope.child();
+            let next = PieceOfWork::exec_streamingly_in(&mut self.reader, &mut scope).await?;
+            let named = scope.into_results();
+            self.scope.extend(named);
+            self.index += 1;
+            Ok(Some(next))
+        } else {
+            if self.mode == InputMode::Array {
+                self.reader.end_array().await?;
+            }
+            self.state = WorkReaderState::Done;
+            Ok(None)
+        }
+    }
+}
+
+#[cfg(feature = "python")]
+mod strompychan {
+    use std::{
+        pin::{pin, Pin},
+        sync::{
+            atomic::{AtomicBool, Ordering},
+            Arc, PoisonError,
+        },
+        task::{ready, Context, Poll},
+    };
+
+    use futures::{
+        channel::{mpsc, oneshot},
+        future::{self, Either, Shared},
+        lock::Mutex,
+        AsyncRead, FutureExt, SinkExt, StreamExt,
+    };
+    use pychan::{py_bytes::PyBytesSender, reader::PyBytesReader};
+    use pyo3::{
+        exceptions::PyStopAsyncIteration,
+        pyclass, pymethods,
+        types::{PyAnyMethods, PyBytes, PyList},
+        IntoPy, Py, PyErr, PyObject, PyRef, PyResult, Python,
+    };
+
+    use crate::{
+        py::{into_py_result, PyOutput},
+        writer::ChannelWriter,
+        BinaryFormat, BinaryWorkReader, InputMode, ItemError, StrompyError, StrompyResult,
+        WorkReader, WorkResult, WorkWriter,
+    };
+
+    /// An [AsyncRead] that keeps track of whether the end of its input was reached
+    struct EofTracking<R> {
+        reader: R,
+        eof: Arc<AtomicBool>,
+    }
+
+    impl<R: AsyncRead + Unpin> AsyncRead for EofTracking<R> {
+        fn poll_read(
+            self: Pin<&mut Self>,
+            cx: &mut Context<'_>,
+            buf: &mut [u8],
+        ) -> Poll<std::io::Result<usize>> {
+            let this = self.get_mut();
+            let read = ready!(Pin::new(&mut this.reader).poll_read(cx, buf))?;
+            if read == 0 && !buf.is_empty() {
+                this.eof.store(true, Ordering::Relaxed);
+            }
+            Poll::Ready(Ok(read))
+        }
+    }
+
+    /// A reader of work in any of the supported formats
+    enum AnyWorkReader {
+        Json(WorkReader<EofTracking<PyBytesReader>>),
+        Binary(BinaryWorkReader<EofTracking<PyBytesReader>>),
+    }
+
+    impl AnyWorkReader {
+        /// Read the next item, continuing after errors in a
+        /// single piece of work if `continue_on_error` is set
+        async fn next(
+            &mut self,
+            continue_on_error: bool,
+        ) -> StrompyResult<Option<Result<WorkResult, ItemError>>> {
+            match (self, continue_on_error) {
+                (AnyWorkReader::Json(reader), true) => reader.next_item().await,
+                (AnyWorkReader::Binary(reader), true) => reader.next_item().await,
+                (AnyWorkReader::Json(reader), false) => Ok(reader.next().await?.map(Ok)),
+                (AnyWorkReader::Binary(reader), false) => Ok(reader.next().await?.map(Ok)),
+            }
+        }
+
+        /// Check all remaining work without executing it
+        async fn validate(self) -> Vec<StrompyError> {
+            match self {
+                AnyWorkReader::Json(reader) => reader.validate().await,
+                AnyWorkReader::Binary(reader) => reader.validate().await,
+            }
+        }
+    }
+
+    /// An error in a single piece of work, which a [StrompyJsonReader] that continues
+    /// on errors returns in place of its result. `index` is the index of the piece
+    /// of work in the stream, and `error` is the exception it raised.
+    #[pyclass(name = "ItemError", frozen, get_all)]
+    pub struct StrompyItemError {
+        index: usize,
+        error: PyObject,
+    }
+
+    impl StrompyItemError {
+        fn new(py: Python<'_>, ItemError { index, error }: ItemError) -> Self {
+            let error = PyErr::from(error).into_value(py).into_any();
+            Self { index, error }
+        }
+    }
+
+    #[pymethods]
+    impl StrompyItemError {
+        fn __repr__(&self, py: Python<'_>) -> PyResult<String> {
+            let error = self.error.bind(py).repr()?;
+            Ok(format!("ItemError(index={}, error={error})", self.index))
+        }
+    }
+
+    /// Reads [PieceOfWork](crate::PieceOfWork)s from a stream of bytes fed from
+    /// Python, executing them one by one. Supports `async for`, and can be used
+    /// as an async context manager that closes the reader on exit.
+    /// Despite its name, it reads CBOR and MessagePack as well.
+    #[pyclass]
+    #[derive(Clone)]
+    pub struct StrompyJsonReader {
+        /// The reader, or `None` if it has been closed
+        inner: Arc<Mutex<Option<AnyWorkReader>>>,
+        /// Set once the input has ended
+        eof: Arc<AtomicBool>,
+        /// Used to stop any pending read, or `None` if the reader was stopped already
+        stop: Arc<std::sync::Mutex<Option<oneshot::Sender<Stop>>>>,
+        /// Completes once the reader is closed or cancelled
+        stopped: Shared<oneshot::Receiver<Stop>>,
+        /// Whether errors in a single piece of work are returned
+        /// as a [StrompyItemError] instead of raised
+        continue_on_error: bool,
+    }
+
+    impl StrompyJsonReader {
+        /// Create a reader of work in `format`, or in JSON if `format` is `None`
+        pub fn new(
+            reader: PyBytesReader,
+            mode: InputMode,
+            format: Option<BinaryFormat>,
+            continue_on_error: bool,
+        ) -> Self {
+            let eof = Arc::new(AtomicBool::new(false));
+            let reader = EofTracking {
+                reader,
+                eof: eof.clone(),
+            };
+            let reader = match format {
+                None => AnyWorkReader::Json(WorkReader::with_mode(reader, mode)),
+                Some(format) => {
+                    AnyWorkReader::Binary(BinaryWorkReader::new(reader, format, mode))
+                }
+            };
+            let (stop, stopped) = oneshot::channel();
+            Self {
+                inner: Arc::new(Mutex::new(Some(reader))),
+                eof,
+                stop: Arc::new(std::sync::Mutex::new(Some(stop))),
+                stopped: stopped.shared(),
+                continue_on_error,
+            }
+        }
+
+        /// Read and execute the next piece of work. Fails with [StrompyError::Cancelled]
+        /// or [StrompyError::Closed] if the reader is cancelled or closed before the
+        /// piece of work is read completely. Errors in a single piece of work are
+        /// returned as an [ItemError] if the reader continues on errors.
+        pub async fn next(&self) -> StrompyResult<Option<Result<WorkResult, ItemError>>> {
+            let continue_on_error = self.continue_on_error;
+            let next = async {
+                match self.inner.lock().await.as_mut() {
+                    Some(reader) => reader.next(continue_on_error).await,
+                    None => Err(StrompyError::Closed),
+                }
+            };
+
+            let res = match future::select(self.stopped.clone(), pin!(next)).await {
+                Either::Left((stop, _)) => return Err(Stop::error(stop)),
+                Either::Right((res, _)) => res,
+            };
+            res.map_err(|e| self.sender_closed(e))
+        }
+
+        /// Check all remaining work without executing it, returning every problem
+        /// found, and close the reader. Fails with [StrompyError::Cancelled] or
+        /// [StrompyError::Closed] if the reader is cancelled or closed before
+        /// the input is read completely.
+        pub async fn validate(&self) -> StrompyResult<Vec<StrompyError>> {
+            let validate = async {
+                match self.inner.lock().await.take() {
+                    Some(reader) => Ok(reader.validate().await),
+                    None => Err(StrompyError::Closed),
+                }
+            };
+
+            let problems = match future::select(self.stopped.clone(), pin!(validate)).await {
+                Either::Left((stop, _)) => return Err(Stop::error(stop)),
+                Either::Right((problems, _)) => problems?,
+            };
+            Ok(problems
+                .into_iter()
+                .map(|e| self.sender_closed(e))
+                .collect())
+        }
+
+        /// Input that ends while a piece of work is being read
+        /// means the sender went away before it was done
+        fn sender_closed(&self, e: StrompyError) -> StrompyError {
+            match e.kind() {
+                StrompyError::Struson(_) | StrompyError::Cbor(_) | StrompyError::MessagePack(_)
+                    if self.eof.load(Ordering::Relaxed) =>
+                {
+                    match e {
+                        StrompyError::Located(_, location) => {
+                            StrompyError::SenderClosed.at(location)
+                        }
+                        _ => StrompyError::SenderClosed,
+                    }
+                }
+                _ => e,
+            }
+        }
+
+        /// Close the reader, dropping the underlying [PyBytesReader]. Any pending
+        /// call to [StrompyJsonReader::next] fails with [StrompyError::Closed].
+        pub async fn close(&self) {
+            self.stop(Stop::Closed);
+            self.inner.lock().await.take();
+        }
+
+        /// Abort reading, making any pending and future calls to
+        /// [StrompyJsonReader::next] fail, and close the reader
+        pub async fn cancel(&self) {
+            self.stop(Stop::Cancelled);
+            self.close().await;
+        }
+
+        /// Make any pending read fail for reason `stop`,
+        /// unless the reader was stopped already
+        fn stop(&self, stop: Stop) {
+            let sender = self
+                .stop
+                .lock()
+                .unwrap_or_else(PoisonError::into_inner)
+                .take();
+            if let Some(sender) = sender {
+                let _ = sender.send(stop);
+            }
+        }
+    }
+
+    /// Why a [StrompyJsonReader] stopped reading before the input ended
+    #[derive(Debug, Clone, Copy)]
+    enum Stop {
+        Closed,
+        Cancelled,
+    }
+
+    impl Stop {
+        /// The error a pending read fails with once the reader is stopped
+        fn error(stop: Result<Stop, oneshot::Canceled>) -> StrompyError {
+            match stop {
+                Ok(Stop::Closed) => StrompyError::Closed,
+                Ok(Stop::Cancelled) | Err(_) => StrompyError::Cancelled,
+            }
+        }
+    }
+
+    #[pymethods]
+    impl StrompyJsonReader {
+        #[pyo3(name = "next", signature = (*, numpy = false, matrix = false))]
+        async fn next_py(&self, numpy: bool, matrix: bool) -> PyResult<Option<PyObject>> {
+            let output = PyOutput::new(numpy, matrix)?;
+            let Some(next) = self.next().await? else {
+                return Ok(None);
+            };
+
+            Python::with_gil(|py| {
+                let next = match next {
+                    Ok(next) => next,
+                    Err(e) => return Ok(Some(StrompyItemError::new(py, e).into_py(py))),
+                };
+                match next {
+                    WorkResult::Matrix(next) if output == PyOutput::List => {
+                        Ok(Some(PyList::new_bound(py, next).into_any().unbind()))
+                    }
+                    next => into_py_result(py, next, output).map(Some),
+                }
+            })
+        }
+
+        /// Check all remaining work without executing it, returning a list with
+        /// an exception for every problem found. Closes the reader.
+        #[pyo3(name = "validate")]
+        async fn validate_py(&self) -> PyResult<Vec<PyObject>> {
+            let problems = self.validate().await?;
+            Ok(Python::with_gil(|py| {
+                problems
+                    .into_iter()
+                    .map(|e| PyErr::from(e).into_value(py).into_any().unbind())
+                    .collect()
+            }))
+        }
+
+        #[pyo3(name = "close")]
+        async fn close_py(&self) {
+            self.close().await
+        }
+
+        #[pyo3(name = "cancel")]
+        async fn cancel_py(&self) {
+            self.cancel().await
+        }
+
+        fn __aiter__(slf: PyRef<'_, Self>) -> PyRef<'_, Self> {
+            slf
+        }
+
+        async fn __anext__(&self) -> PyResult<PyObject> {
+            match self.next_py(false, false).await? {
+                Some(next) => Ok(next),
+                None => Err(PyStopAsyncIteration::new_err(())),
+            }
+        }
+
+        async fn __aenter__(&self) -> Self {
+            self.clone()
+        }
+
+        async fn __aexit__(
+            &self,
+            _exc_type: PyObject,
+            _exc_value: PyObject,
+            _traceback: PyObject,
+        ) -> bool {
+            self.close().await;
+            false
+        }
+    }
+
+    /// Sends bytes to a [StrompyJsonReader]. Can be used as an async context manager
+    /// that closes the sender on exit, which signals the end of the input.
+    #[pyclass]
+    #[derive(Clone)]
+    pub struct StrompyBytesSender {
+        /// The sender, or `None` if it has been closed
+        inner: Arc<Mutex<Option<PyBytesSender>>>,
+    }
+
+    impl StrompyBytesSender {
+        pub fn new(sender: PyBytesSender) -> Self {
+            Self {
+                inner: Arc::new(Mutex::new(Some(sender))),
+            }
+        }
+
+        /// Close the sender, dropping the underlying [PyBytesSender]
+        pub async fn close(&self) {
+            self.inner.lock().await.take();
+        }
+    }
+
+    #[pymethods]
+    impl StrompyBytesSender {
+        /// Send a chunk of bytes, waiting for the reader
+        /// to catch up if the channel is full
+        async fn send(&self, bytes: Py<PyBytes>) -> PyResult<()> {
+            match self.inner.lock().await.as_mut() {
+                Some(sender) => Ok(sender.send(bytes).await?),
+                None => Err(StrompyError::Closed.into()),
+            }
+        }
+
+        #[pyo3(name = "close")]
+        async fn close_py(&self) {
+            self.close().await
+        }
+
+        async fn __aenter__(&self) -> Self {
+            self.clone()
+        }
+
+        async fn __aexit__(
+            &self,
+            _exc_type: PyObject,
+            _exc_value: PyObject,
+            _traceback: PyObject,
+        ) -> bool {
+            self.close().await;
+            false
+        }
+    }
+
+    /// Writes results as a JSON array, sending the output bytes
+    /// to a [StrompyBytesReceiver]
+    #[pyclass]
+    #[derive(Clone)]
+    pub struct StrompyJsonWriter {
+        /// The writer, or `None` if it has been closed
+        inner: Arc<Mutex<Option<WorkWriter<ChannelWriter>>>>,
+    }
+
+    impl StrompyJsonWriter {
+        pub fn new(writer: ChannelWriter) -> Self {
+            Self {
+                inner: Arc::new(Mutex::new(Some(WorkWriter::new(writer)))),
+            }
+        }
+
+        /// Close the JSON array and flush the output
+        pub async fn close(&self) -> StrompyResult<()> {
+            match self.inner.lock().await.take() {
+                Some(writer) => writer.finish().await,
+                None => Ok(()),
+            }
+        }
+
+        /// Execute all work `reader` yields, writing the results as they come in
+        async fn write_results(&self, reader: &StrompyJsonReader) -> StrompyResult<()> {
+            let mut inner = self.inner.lock().await;
+            let writer = inner.as_mut().ok_or(StrompyError::Closed)?;
+            while let Some(res) = reader.next().await? {
+                match res {
+                    Ok(res) => writer.write(&res).await?,
+                    Err(e) => writer.write_item_error(&e).await?,
+                }
+            }
+            Ok(())
+        }
+    }
+
+    #[pymethods]
+    impl StrompyJsonWriter {
+        /// Execute all work `reader` yields, writing the results as they come in.
+        /// Closes the writer once `reader` is exhausted, or once reading or
+        /// writing fails, in which case the output ends with the results
+        /// written so far and the error is raised.
+        async fn write_all(&self, reader: StrompyJsonReader) -> PyResult<()> {
+            let written = self.write_results(&reader).await;
+            let closed = self.close().await;
+            written?;
+            closed?;
+            Ok(())
+        }
+
+        #[pyo3(name = "close")]
+        async fn close_py(&self) -> PyResult<()> {
+            self.close().await?;
+            Ok(())
+        }
+    }
+
+    /// Receives the bytes written by a [StrompyJsonWriter]. Supports `async for`.
+    #[pyclass]
+    #[derive(Clone)]
+    pub struct StrompyBytesReceiver {
+        rx: Arc<Mutex<mpsc::Receiver<Vec<u8>>>>,
+    }
+
+    impl StrompyBytesReceiver {
+        pub fn new(rx: mpsc::Receiver<Vec<u8>>) -> Self {
+            Self {
+                rx: Arc::new(Mutex::new(rx)),
+            }
+        }
+    }
+
+    #[pymethods]
+    impl StrompyBytesReceiver {
+        /// Receive the next chunk of bytes, or `None` once the writer is closed
+        async fn recv(&self) -> Option<Py<PyBytes>> {
+            let chunk = self.rx.lock().await.next().await?;
+            Some(Python::with_gil(|py| PyBytes::new_bound(py, &chunk).unbind()))
+        }
+
+        fn __aiter__(slf: PyRef<'_, Self>) -> PyRef<'_, Self> {
+            slf
+        }
+
+        async fn __anext__(&self) -> PyResult<Py<PyBytes>> {
+            self.recv()
+                .await
+                .ok_or_else(|| PyStopAsyncIteration::new_err(()))
+        }
+    }
+}
+
+#[cfg(feature = "python")]
+mod py {
+    use std::sync::{Arc, Mutex, PoisonError};
+
+    use numpy::{npyffi::NPY_ORDER, PyArray1, PyArray2, PyArrayMethods, PyReadonlyArray2};
+    use pyo3::{
+        exceptions::{PyRuntimeError, PyValueError},
+        prelude::*,
+        types::{PyBytes, PyDict},
+    };
+    use rayon::{ThreadPool, ThreadPoolBuilder};
+
+    use crate::{
+        builder::WorkBuilder,
+        error::exceptions,
+        matrix::PyMatrix,
+        storage::Storage,
+        strompychan::{
+            StrompyBytesReceiver, StrompyBytesSender, StrompyItemError, StrompyJsonReader,
+            StrompyJsonWriter,
+        },
+        writer::ChannelWriter,
+        BinaryFormat, InputMode, MatrixBuf, Shape, StrompyResult, WorkResult,
+    };
+
+    impl From<MatrixBuf> for Vec<Vec<f64>> {
+        /// The rows of the matrix
+        fn from(m: MatrixBuf) -> Self {
+            m.view()
+                .row_iter()
+                .map(|row| row.iter().copied().collect())
+                .collect()
+        }
+    }
+
+    impl MatrixBuf {
+        /// Copy a two-dimensional NumPy array into a new [MatrixBuf]
+        pub(crate) fn from_ndarray(array: PyReadonlyArray2<'_, f64>) -> StrompyResult<Self> {
+            let array = array.as_array();
+            let mut d = Storage::new();
+            // Whatever the layout of the array, store its elements column by column
+            for column in array.columns() {
+                for &x in column {
+                    d.push(x)?;
+                }
+            }
+            Self::new(d, array.ncols())
+        }
+
+        /// Copy the matrix into a new two-dimensional NumPy array, which
+        /// keeps the elements column by column, in Fortran order
+        pub(crate) fn to_ndarray<'py>(&self, py: Python<'py>) -> PyResult<Bound<'py, PyArray2<f64>>> {
+            let Shape { rows, cols } = self.shape();
+            PyArray1::from_slice_bound(py, &self.d[..])
+                .reshape_with_order([rows, cols], NPY_ORDER::NPY_FORTRANORDER)
+        }
+    }
+
+    /// What results are converted into for Python, see [into_py_matrix]
+    #[derive(Debug, Clone, Copy, PartialEq, Eq)]
+    pub(crate) enum PyOutput {
+        /// A list of rows
+        List,
+        /// A two-dimensional NumPy array
+        NumPy,
+        /// A [PyMatrix]
+        Matrix,
+    }
+
+    impl PyOutput {
+        /// The output the `numpy` and `matrix` keyword arguments ask for,
+        /// of which at most one can be set
+        pub(crate) fn new(numpy: bool, matrix: bool) -> PyResult<Self> {
+            match (numpy, matrix) {
+                (false, false) => Ok(Self::List),
+                (true, false) => Ok(Self::NumPy),
+                (false, true) => Ok(Self::Matrix),
+                (true, true) => Err(PyValueError::new_err(
+                    "Results can be returned either as NumPy arrays or as matrices, not both",
+                )),
+            }
+        }
+    }
+
+    /// Convert a result matrix into the Python object `output` asks for
+    pub(crate) fn into_py_matrix(
+        py: Python<'_>,
+        res: MatrixBuf,
+        output: PyOutput,
+    ) -> PyResult<PyObject> {
+        match output {
+            PyOutput::List => Ok(Vec::<Vec<f64>>::from(res).into_py(py)),
+            PyOutput::NumPy => Ok(res.to_ndarray(py)?.into_any().unbind()),
+            PyOutput::Matrix => Ok(PyMatrix::from(res).into_py(py)),
+        }
+    }
+
+    /// Convert a result into Python objects, see [into_py_matrix]. The factors
+    /// of a decomposition are converted into a dict keyed by their names.
+    pub(crate) fn into_py_result(
+        py: Python<'_>,
+        res: WorkResult,
+        output: PyOutput,
+    ) -> PyResult<PyObject> {
+        match res {
+            WorkResult::Matrix(m) => into_py_matrix(py, m, output),
+            WorkResult::Factors(factors) => {
+                let dict = PyDict::new_bound(py);
+                for (name, m) in factors {
+                    dict.set_item(name, into_py_matrix(py, m, output)?)?;
+                }
+                Ok(dict.into_any().unbind())
+            }
+        }
+    }
+
+    /// Parse the name of an input format, which is `None` for JSON
+    fn parse_format(format: &str) -> PyResult<Option<BinaryFormat>> {
+        match format {
+            "json" => Ok(None),
+            format => format.parse().map(Some).map_err(PyValueError::new_err),
+        }
+    }
+
+    /// Get a thread pool with `threads` threads. The last pool is kept
+    /// around, so that it is not rebuilt on every call with the same number
+    /// of threads. Any other pool shuts down once the calls using it are done.
+    fn thread_pool(threads: usize) -> PyResult<Arc<ThreadPool>> {
+        static POOL: Mutex<Option<(usize, Arc<ThreadPool>)>> = Mutex::new(None);
+
+        // Rayon would take 0 to mean as many threads as there are CPUs
+        if threads == 0 {
+            return Err(PyValueError::new_err(
+                "Number of threads must be at least 1",
+            ));
+        }
+
+        let mut cached = POOL.lock().unwrap_or_else(PoisonError::into_inner);
+        if let Some((_, pool)) = cached.as_ref().filter(|(count, _)| *count == threads) {
+            return Ok(pool.clone());
+        }
+
+        let pool = ThreadPoolBuilder::new()
+            .num_threads(threads)
+            .build()
+            .map_err(|e| PyRuntimeError::new_err(e.to_string()))?;
+        let pool = Arc::new(pool);
+        *cached = Some((threads, pool.clone()));
+        Ok(pool)
+    }
+
+    /// Execute an array of work. `format` is one of `"json"`, `"cbor"` or `"msgpack"`.
+    /// Pieces of work are executed in parallel, on `threads` threads if given,
+    /// or on as many threads as there are CPUs otherwise. The GIL is released
+    /// while the work executes. Results are lists of rows, or NumPy arrays
+    /// with `numpy=True`, or `Matrix` objects with `matrix=True`.
     #[pyfunction]
-    async fn feed_bytes(mut writer: PyBytesSender, bytes: Py<PyBytes>) -> PyResult<()> {
-        writer.send(bytes).await?;
-        Ok(())
+    #[pyo3(signature = (json_bytes, *, numpy = false, matrix = false, format = "json", threads = None))]
+    fn exec(
+        py: Python<'_>,
+        json_bytes: &[u8],
+        numpy: bool,
+        matrix: bool,
+        format: &str,
+        threads: Option<usize>,
+    ) -> PyResult<Vec<PyObject>> {
+        let output = PyOutput::new(numpy, matrix)?;
+        let format = parse_format(format)?;
+        let pool = threads.map(thread_pool).transpose()?;
+
+        let run = || match format {
+            None => crate::exec(json_bytes),
+            Some(format) => crate::exec_binary(json_bytes, format),
+        };
+        let res = py.allow_threads(|| match pool {
+            Some(pool) => pool.install(run),
+            None => run(),
+        })?;
+
+        res.into_iter()
+            .map(|res| into_py_result(py, res, output))
+            .collect()
+    }
+
+    /// Check an array of work without executing it. `format` is one of `"json"`,
+    /// `"cbor"` or `"msgpack"`. Returns a list with an exception for every problem
+    /// found, which is empty if the work is valid.
+    #[pyfunction]
+    #[pyo3(signature = (json_bytes, *, format = "json"))]
+    fn validate(py: Python<'_>, json_bytes: &[u8], format: &str) -> PyResult<Vec<PyObject>> {
+        let format = parse_format(format)?;
+        let problems = py.allow_threads(|| match format {
+            None => crate::validate(json_bytes),
+            Some(format) => crate::validate_binary(json_bytes, format),
+        });
+
+        Ok(problems
+            .into_iter()
+            .map(|e| PyErr::from(e).into_value(py).into_any().unbind())
+            .collect())
+    }
+
+    /// Create a channel through which work is fed. `mode` is one of `"array"`,
+    /// for a single array of work, and `"ndjson"` or `"concatenated"`,
+    /// for any number of top-level pieces of work. `format` is one of
+    /// `"json"`, `"cbor"` or `"msgpack"`. At most `capacity` chunks of input
+    /// are buffered, after which sending waits for the reader to catch up.
+    ///
+    /// With `on_error="continue"`, an error in a single piece of work does not
+    /// end reading. Instead, the reader returns an `ItemError` in its place and
+    /// continues with the next piece of work. Errors after which the next piece
+    /// of work cannot be found, like malformed input, are raised either way.
+    #[pyfunction]
+    #[pyo3(signature = (*, mode = "array", format = "json", capacity = 16, on_error = "raise"))]
+    fn channel(
+        mode: &str,
+        format: &str,
+        capacity: usize,
+        on_error: &str,
+    ) -> PyResult<(StrompyBytesSender, StrompyJsonReader)> {
+        let mode: InputMode = mode.parse().map_err(PyValueError::new_err)?;
+        let format = parse_format(format)?;
+        let continue_on_error = match on_error {
+            "raise" => false,
+            "continue" => true,
+            on_error => {
+                return Err(PyValueError::new_err(format!(
+                    r#"Unknown error handling "{on_error}", expected "raise" or "continue""#
+                )))
+            }
+        };
+        if capacity == 0 {
+            return Err(PyValueError::new_err("Channel capacity must be at least 1"));
+        }
+        let (tx, rx) = pychan::py_bytes::channel(capacity);
+        let reader = rx.into_reader();
+        let reader = StrompyJsonReader::new(reader, mode, format, continue_on_error);
+
+        Ok((StrompyBytesSender::new(tx), reader))
+    }
+
+    /// Create a channel through which results are written as JSON.
+    /// At most `capacity` chunks of output are buffered.
+    #[pyfunction]
+    #[pyo3(signature = (capacity = 16))]
+    fn json_channel(capacity: usize) -> (StrompyJsonWriter, StrompyBytesReceiver) {
+        let (tx, rx) = futures::channel::mpsc::channel(capacity);
+        let writer = StrompyJsonWriter::new(ChannelWriter::new(tx));
+        let receiver = StrompyBytesReceiver::new(rx);
+
+        (writer, receiver)
+    }
+
+    #[pyfunction]
+    async fn feed_bytes(writer: StrompyBytesSender, bytes: Py<PyBytes>) -> PyResult<()> {
+        writer.send(bytes).await
     }
 
     #[pymodule]
-    fn strompy(_py: Python, m: &Bound<PyModule>) -> PyResult<()> {
+    fn strompy(py: Python, m: &Bound<PyModule>) -> PyResult<()> {
         m.add_function(wrap_pyfunction!(exec, m)?)?;
+        m.add_function(wrap_pyfunction!(validate, m)?)?;
         m.add_function(wrap_pyfunction!(channel, m)?)?;
+        m.add_function(wrap_pyfunction!(json_channel, m)?)?;
         m.add_class::<StrompyJsonReader>()?;
+        m.add_class::<StrompyItemError>()?;
+        m.add_class::<StrompyBytesSender>()?;
+        m.add_class::<StrompyJsonWriter>()?;
+        m.add_class::<StrompyBytesReceiver>()?;
+        m.add_class::<WorkBuilder>()?;
+        m.add_class::<PyMatrix>()?;
         m.add_function(wrap_pyfunction!(feed_bytes, m)?)?;
+
+        m.add("StrompyError", py.get_type_bound::<exceptions::StrompyError>())?;
+        m.add("JsonSyntaxError", py.get_type_bound::<exceptions::JsonSyntaxError>())?;
+        m.add("DecodeError", py.get_type_bound::<exceptions::DecodeError>())?;
+        m.add("UnexpectedKeyError", py.get_type_bound::<exceptions::UnexpectedKeyError>())?;
+        m.add("CapacityError", py.get_type_bound::<exceptions::CapacityError>())?;
+        m.add("DimensionError", py.get_type_bound::<exceptions::DimensionError>())?;
+        m.add("LinAlgError", py.get_type_bound::<exceptions::LinAlgError>())?;
+        m.add("UnknownOperationError", py.get_type_bound::<exceptions::UnknownOperationError>())?;
+        m.add("UnknownReferenceError", py.get_type_bound::<exceptions::UnknownReferenceError>())?;
+        m.add("InvalidNumberError", py.get_type_bound::<exceptions::InvalidNumberError>())?;
         Ok(())
     }
 }
@@ -338,7 +2690,12 @@ mod py {
 mod test {
     use struson::reader::{JsonReader, JsonStreamReader};
 
-    use crate::PieceOfWork;
+    use crate::{
+        error::{Location, StrompyError},
+        exec, exec_binary, validate, validate_binary, BinaryFormat, BinaryWorkReader, InputMode,
+        ItemError, MatrixBuf, OutputMode, PieceOfWork, Shape, WorkReader, WorkResult, WorkWriter,
+        MATRIX_CAPACITY, MAX_DEPTH, OP_CHAIN_CAPACITY,
+    };
 
     #[test]
     fn it_deserializes() {
@@ -350,8 +2707,8 @@ mod test {
     fn it_works() {
         let json = include_str!("../op.json");
         let [work]: [PieceOfWork; 1] = serde_json::from_str(json).unwrap();
-        let res = work.exec();
-        assert_eq!(res.view(), nalgebra::matrix![1586.0]);
+        let res = work.exec().unwrap();
+        assert_eq!(res.matrix().unwrap().view(), nalgebra::matrix![1586.0]);
     }
 
     #[tokio::test]
@@ -365,10 +2722,1350 @@ mod test {
         let res = PieceOfWork::exec_streamingly(&mut json_reader)
             .await
             .unwrap();
-        assert_eq!(res.view(), nalgebra::matrix![1586.0]);
+        assert_eq!(res.matrix().unwrap().view(), nalgebra::matrix![1586.0]);
 
         assert!(!json_reader.has_next().await.unwrap());
 
         json_reader.end_array().await.unwrap();
     }
+
+    /// Executes the single [PieceOfWork] in `json` using both the
+    /// serde-based and the streaming implementation
+    async fn exec_both(json: &str) -> (MatrixBuf, MatrixBuf) {
+        let [work]: [PieceOfWork; 1] = serde_json::from_str(json).unwrap();
+        let res = work.exec().unwrap();
+
+        let mut json_reader = JsonStreamReader::new(json.as_bytes());
+        json_reader.begin_array().await.unwrap();
+        let res_streamingly = PieceOfWork::exec_streamingly(&mut json_reader)
+            .await
+            .unwrap();
+        json_reader.end_array().await.unwrap();
+
+        (
+            res.into_matrix().unwrap(),
+            res_streamingly.into_matrix().unwrap(),
+        )
+    }
+
+    #[tokio::test]
+    async fn it_multiplies_and_transposes() {
+        let json = r#"[{
+            "lhs": { "d": [1, 2, 3, 4, 5, 6], "n": 3 },
+            "op": [
+                { "code": "transpose" },
+                { "code": "matmul", "rhs": { "d": [1, 0, 0, 1], "n": 2 } },
+                { "code": "scale", "factor": 2 }
+            ]
+        }]"#;
+        let (res, res_streamingly) = exec_both(json).await;
+        let expected = nalgebra::matrix![
+            2.0, 4.0;
+            6.0, 8.0;
+            10.0, 12.0
+        ];
+        assert_eq!(res.view(), expected);
+        assert_eq!(res_streamingly.view(), expected);
+    }
+
+    #[tokio::test]
+    async fn it_adds_and_subtracts() {
+        let json = r#"[{
+            "lhs": { "d": [1, 2, 3, 4], "n": 2 },
+            "op": [
+                { "code": "add", "rhs": { "d": [10, 20, 30, 40], "n": 2 } },
+                { "code": "sub", "rhs": { "d": [1, 1, 1, 1], "n": 2 } }
+            ]
+        }]"#;
+        let (res, res_streamingly) = exec_both(json).await;
+        let expected = nalgebra::matrix![
+            10.0, 32.0;
+            21.0, 43.0
+        ];
+        assert_eq!(res.view(), expected);
+        assert_eq!(res_streamingly.view(), expected);
+    }
+
+    #[tokio::test]
+    async fn it_accepts_keys_in_any_order() {
+        let json = r#"[{
+            "op": [
+                { "rhs": { "n": 2, "d": [10, 20, 30, 40] }, "code": "add" },
+                { "comment": "halve it", "factor": 0.5, "code": "scale" }
+            ],
+            "version": 1,
+            "lhs": { "n": 2, "extra": [1, 2, 3], "d": [2, 4, 6, 8] }
+        }]"#;
+        let (res, res_streamingly) = exec_both(json).await;
+        let expected = nalgebra::matrix![
+            6.0, 18.0;
+            12.0, 24.0
+        ];
+        assert_eq!(res.view(), expected);
+        assert_eq!(res_streamingly.view(), expected);
+    }
+
+    #[tokio::test]
+    async fn it_rejects_missing_and_duplicate_keys() {
+        async fn exec_streamingly(json: &str) -> Result<WorkResult, StrompyError> {
+            let mut json_reader = JsonStreamReader::new(json.as_bytes());
+            PieceOfWork::exec_streamingly(&mut json_reader).await
+        }
+
+        let missing = r#"{ "lhs": { "d": [1, 2, 3, 4] }, "op": [] }"#;
+        assert!(matches!(
+            exec_streamingly(missing).await,
+            Err(StrompyError::MissingKey("n"))
+        ));
+
+        let duplicate = r#"{ "lhs": { "d": [1], "n": 1 }, "op": [], "op": [] }"#;
+        assert!(matches!(
+            exec_streamingly(duplicate).await,
+            Err(StrompyError::DuplicateKey("op"))
+        ));
+
+        let unknown = r#"{ "lhs": { "d": [1], "n": 1 }, "op": [{ "code": "frobnicate" }] }"#;
+        assert!(matches!(
+            exec_streamingly(unknown).await,
+            Err(StrompyError::UnknownOperation(code)) if code == "frobnicate"
+        ));
+    }
+
+    #[tokio::test]
+    async fn it_locates_errors() {
+        let json = "[\n  {\"lhs\": {\"d\": [1, \"x\"], \"n\": 1}, \"op\": []}\n]";
+
+        let mut deserializer = serde_json::Deserializer::from_str(json);
+        let e = serde_path_to_error::deserialize::<_, Vec<PieceOfWork>>(&mut deserializer)
+            .unwrap_err();
+        let location = Location::of_serde(&e, json.as_bytes());
+        assert_eq!(location.json_path(), "$[0].lhs.d[1]");
+        assert_eq!(location.line, Some(2));
+        let line_start = json.find('\n').unwrap() as u64 + 1;
+        assert_eq!(
+            location.offset,
+            Some(line_start + location.column.unwrap() - 1)
+        );
+
+        let mut json_reader = JsonStreamReader::new(json.as_bytes());
+        json_reader.begin_array().await.unwrap();
+        assert!(PieceOfWork::exec_streamingly(&mut json_reader)
+            .await
+            .is_err());
+        let location = Location::of_reader(&json_reader);
+        assert_eq!(location.json_path(), "$[0].lhs.d[1]");
+        assert_eq!(location.line, Some(2));
+    }
+
+    /// Runs `json` through both [exec] and [WorkReader], returning the first
+    /// error each of them encountered
+    async fn first_errors(json: &str) -> (StrompyError, StrompyError) {
+        let err = exec(json.as_bytes()).unwrap_err();
+
+        let mut work_reader = WorkReader::new(json.as_bytes());
+        let err_streamingly = loop {
+            match work_reader.next().await {
+                Ok(Some(_)) => continue,
+                Ok(None) => panic!("Reading {json} did not fail"),
+                Err(e) => break e,
+            }
+        };
+
+        // The reader cannot be used anymore after an error
+        assert!(matches!(
+            work_reader.next().await,
+            Err(StrompyError::Poisoned)
+        ));
+
+        (err, err_streamingly)
+    }
+
+    #[tokio::test]
+    async fn it_rejects_malformed_input() {
+        let valid = r#"{ "lhs": { "d": [1], "n": 1 }, "op": [] }"#;
+        let malformed = [
+            "",
+            "{}",
+            r#"[{ "lhs": { "d": [1,], "n": 1 }, "op": [] }]"#,
+            r#"[{ "lhs": { "d": [1], "n": -1 }, "op": [] }]"#,
+            r#"[{ "lhs": { "d": [true], "n": 1 }, "op": [] }]"#,
+            r#"[{ "lhs": { "d": [1], "n": 1 }, "op": {} }]"#,
+            r#"[{ "lhs": { "d": [1], "n": 1 }, "op": [{ "code": 1 }] }]"#,
+        ];
+        for json in malformed {
+            first_errors(json).await;
+        }
+
+        let truncated = format!("[{valid}, {valid}");
+        for end in 0..truncated.len() {
+            first_errors(&truncated[..end]).await;
+        }
+    }
+
+    #[tokio::test]
+    async fn it_rejects_oversized_input() {
+        let nested = format!("{}{}", "[".repeat(100_000), "]".repeat(100_000));
+        let deeply_nested = format!(r#"[{{ "lhs": {{ "d": [1], "n": 1 }}, "op": [], "x": {nested} }}]"#);
+        first_errors(&deeply_nested).await;
+
+        let huge_n = r#"[{ "lhs": { "d": [1], "n": 100000000000000000000000 }, "op": [] }]"#;
+        let (err, err_streamingly) = first_errors(huge_n).await;
+        assert!(matches!(err.kind(), StrompyError::Serde(_)));
+        assert!(matches!(
+            err_streamingly.kind(),
+            StrompyError::ParseInt(_)
+        ));
+    }
+
+    #[tokio::test]
+    async fn it_rejects_deeply_nested_operands() {
+        let nested = |depth: usize| {
+            let lhs = r#"{ "code": "transpose", "lhs": "#.repeat(depth);
+            let expr = format!(r#"{lhs}{{ "d": [1], "n": 1 }}{}"#, "}".repeat(depth));
+            format!(r#"[{{ "expr": {expr} }}]"#)
+        };
+
+        let json = nested(100);
+        assert!(exec(json.as_bytes()).is_ok());
+        let mut work_reader = WorkReader::new(json.as_bytes());
+        assert!(work_reader.next().await.unwrap().is_some());
+
+        let (err, err_streamingly) = first_errors(&nested(100_000)).await;
+        assert!(matches!(err.kind(), StrompyError::Serde(_)));
+        assert!(matches!(
+            err_streamingly.kind(),
+            StrompyError::TooDeep {
+                max_depth: MAX_DEPTH
+            }
+        ));
+    }
+
+    #[tokio::test]
+    async fn it_validates_shapes() {
+        for (d, n) in [("[1, 2, 3]", 0), ("[1, 2, 3]", 2), ("[]", 0)] {
+            let json = format!(r#"[{{ "lhs": {{ "d": {d}, "n": {n} }}, "op": [] }}]"#);
+            let (err, err_streamingly) = first_errors(&json).await;
+            for err in [err, err_streamingly] {
+                assert_eq!(err.location().unwrap().json_path(), "$[0].lhs");
+                assert!(matches!(err.kind(), StrompyError::InvalidShape { .. }));
+            }
+        }
+
+        let json = r#"[{
+            "lhs": { "d": [1, 2, 3, 4, 5, 6], "n": 3 },
+            "op": [
+                { "code": "transpose" },
+                { "code": "matmul", "rhs": { "d": [1, 2, 3, 4, 5, 6], "n": 2 } }
+            ]
+        }]"#;
+        let (err, err_streamingly) = first_errors(json).await;
+        for err in [err, err_streamingly] {
+            assert_eq!(err.location().unwrap().json_path(), "$[0].op[1]");
+            assert!(matches!(
+                err.kind(),
+                StrompyError::ShapeMismatch {
+                    op: "matmul",
+                    lhs: Shape { rows: 3, cols: 2 },
+                    rhs: Shape { rows: 3, cols: 2 },
+                }
+            ));
+        }
+
+        let json = r#"[{
+            "lhs": { "d": [1, 2, 3, 4], "n": 2 },
+            "op": [{ "code": "add", "rhs": { "d": [1, 2, 3, 4], "n": 1 } }]
+        }]"#;
+        let (err, err_streamingly) = first_errors(json).await;
+        for err in [err, err_streamingly] {
+            assert!(matches!(
+                err.kind(),
+                StrompyError::ShapeMismatch { op: "add", .. }
+            ));
+        }
+    }
+
+    #[tokio::test]
+    async fn it_writes_results_streamingly() {
+        let json = include_str!("../op.json");
+        let mut work_reader = WorkReader::new(json.as_bytes());
+        let mut out = Vec::new();
+        let mut work_writer = WorkWriter::new(&mut out);
+        while let Some(res) = work_reader.next().await.unwrap() {
+            work_writer.write(&res).await.unwrap();
+        }
+        work_writer.finish().await.unwrap();
+
+        // The output can be read back in as matrix data
+        let [res]: [MatrixBuf; 1] = serde_json::from_slice(&out).unwrap();
+        assert_eq!(res.view(), nalgebra::matrix![1586.0]);
+    }
+
+    #[tokio::test]
+    async fn it_writes_non_finite_elements_as_null() {
+        let json = r#"[{ "lhs": { "d": [-1, 0, 1], "n": 3 }, "op": [{ "code": "log" }] }]"#;
+        let res = exec(json.as_bytes()).unwrap();
+        let mut out = Vec::new();
+        let mut work_writer = WorkWriter::new(&mut out);
+        work_writer.write(&res[0]).await.unwrap();
+        work_writer.finish().await.unwrap();
+
+        let out: serde_json::Value = serde_json::from_slice(&out).unwrap();
+        let d = out[0]["d"].as_array().unwrap();
+        assert!(d[0].is_null() && d[1].is_null());
+        assert_eq!(d[2].as_f64(), Some(0.0));
+    }
+
+    #[tokio::test]
+    async fn it_writes_ndjson_and_errors() {
+        let json = r#"[
+            { "lhs": { "d": [1, 2], "n": 2 }, "op": [] },
+            { "lhs": { "d": [1], "n": 1 }, "op": [{ "code": "add", "rhs": { "d": [1, 2], "n": 2 } }] }
+        ]"#;
+        let mut work_reader = WorkReader::new(json.as_bytes());
+        let mut out = Vec::new();
+        let mut work_writer = WorkWriter::with_mode(&mut out, OutputMode::Ndjson);
+        let res = work_reader.next().await.unwrap().unwrap();
+        work_writer.write(&res).await.unwrap();
+        let err = work_reader.next().await.unwrap_err();
+        work_writer.write_error(&err).await.unwrap();
+        work_writer.finish().await.unwrap();
+
+        let out = String::from_utf8(out).unwrap();
+        let mut lines = out.lines();
+        let res: MatrixBuf = serde_json::from_str(lines.next().unwrap()).unwrap();
+        assert_eq!(res.view(), nalgebra::matrix![1.0, 2.0]);
+        let err: serde_json::Value = serde_json::from_str(lines.next().unwrap()).unwrap();
+        let msg = r#"Dimension error: cannot apply "add" to matrices of shape 1x1 and 1x2"#;
+        assert_eq!(err["error"], msg);
+        assert_eq!(err["path"], "$[1].op[0]");
+        assert_eq!(err["line"], 3);
+        assert!(lines.next().is_none());
+    }
+
+    #[tokio::test]
+    async fn it_reads_multiple_top_level_values() {
+        let piece = |x: u32| format!(r#"{{ "lhs": {{ "d": [{x}], "n": 1 }}, "op": [] }}"#);
+        let ndjson = format!("{}\n{}\n{}\n", piece(1), piece(2), piece(3));
+        let concatenated = format!("{}{} {}", piece(1), piece(2), piece(3));
+
+        for json in [ndjson, concatenated] {
+            let mut work_reader = WorkReader::with_mode(json.as_bytes(), InputMode::Values);
+            for x in [1.0, 2.0, 3.0] {
+                let res = work_reader.next().await.unwrap().unwrap();
+                assert_eq!(res.matrix().unwrap().view(), nalgebra::matrix![x]);
+            }
+            assert!(work_reader.next().await.unwrap().is_none());
+        }
+    }
+
+    #[tokio::test]
+    async fn it_resolves_references() {
+        let json = r#"[
+            { "lhs": { "d": [1, 2, 3, 4], "n": 2 }, "op": [], "as": "a" },
+            { "as": "b", "lhs": { "ref": "a" }, "op": [{ "code": "scale", "factor": 10 }] },
+            { "lhs": { "ref": "a" }, "op": [{ "code": "add", "rhs": { "ref": "b" } }] },
+            { "lhs": { "ref": "b" }, "op": [{ "code": "transpose" }], "as": "b" },
+            { "op": [{ "code": "sub", "rhs": { "ref": "a" } }], "lhs": { "ref": "b" } }
+        ]"#;
+        let expected = [
+            nalgebra::matrix![1.0, 3.0; 2.0, 4.0],
+            nalgebra::matrix![10.0, 30.0; 20.0, 40.0],
+            nalgebra::matrix![11.0, 33.0; 22.0, 44.0],
+            nalgebra::matrix![10.0, 20.0; 30.0, 40.0],
+            nalgebra::matrix![9.0, 17.0; 28.0, 36.0],
+        ];
+
+        let res = exec(json.as_bytes()).unwrap();
+        let mut work_reader = WorkReader::new(json.as_bytes());
+        for (res, &expected) in res.iter().zip(&expected) {
+            let res_streamingly = work_reader.next().await.unwrap().unwrap();
+            assert_eq!(res.matrix().unwrap().view(), expected);
+            assert_eq!(res_streamingly.matrix().unwrap().view(), expected);
+        }
+        assert!(work_reader.next().await.unwrap().is_none());
+
+        // References only resolve to results of earlier work
+        let json = r#"[
+            { "lhs": { "d": [1], "n": 1 }, "op": [{ "code": "add", "rhs": { "ref": "c" } }] },
+            { "lhs": { "d": [1], "n": 1 }, "op": [], "as": "c" }
+        ]"#;
+        let (err, err_streamingly) = first_errors(json).await;
+        for err in [err, err_streamingly] {
+            assert_eq!(err.location().unwrap().json_path(), "$[0].op[0]");
+            assert!(matches!(err.kind(), StrompyError::DanglingRef(name) if name == "c"));
+        }
+
+        // Names do not carry over from one stream to the next
+        let json = r#"[{ "lhs": { "ref": "a" }, "op": [] }]"#;
+        let (err, _) = first_errors(json).await;
+        assert!(matches!(err.kind(), StrompyError::DanglingRef(name) if name == "a"));
+
+        let json = r#"[{ "lhs": { "ref": "a", "d": [1], "n": 1 }, "op": [] }]"#;
+        let (_, err_streamingly) = first_errors(json).await;
+        assert!(matches!(
+            err_streamingly.kind(),
+            StrompyError::ConflictingKeys("ref", "d")
+        ));
+    }
+
+    #[tokio::test]
+    async fn it_evaluates_expressions() {
+        let json = r#"[
+            { "expr": { "code": "add",
+                "lhs": { "code": "matmul", "lhs": { "d": [1, 2, 3, 4], "n": 2 }, "rhs": { "d": [0, 1, 1, 0], "n": 2 } },
+                "rhs": { "code": "matmul", "lhs": { "d": [2, 0, 0, 2], "n": 2 }, "rhs": { "d": [1, 1, 1, 1], "n": 2 } }
+            } },
+            { "as": "t", "expr": { "code": "add",
+                "lhs": { "code": "scale", "factor": 2, "lhs": { "d": [1, 2, 3, 4], "n": 2 }, "as": "s" },
+                "rhs": { "ref": "s" }
+            } },
+            { "lhs": { "ref": "s" }, "op": [{ "code": "sub", "rhs": { "ref": "t" } }] },
+            { "lhs": { "d": [1, 2, 3, 4], "n": 2 }, "op": [
+                { "code": "matmul", "rhs": { "d": [0, 1, 1, 0], "n": 2 } },
+                { "code": "scale", "factor": 2 }
+            ] },
+            { "expr": { "code": "scale", "factor": 2,
+                "lhs": { "code": "matmul", "lhs": { "d": [1, 2, 3, 4], "n": 2 }, "rhs": { "d": [0, 1, 1, 0], "n": 2 } }
+            } }
+        ]"#;
+        let expected = [
+            nalgebra::matrix![5.0, 3.0; 6.0, 4.0],
+            nalgebra::matrix![4.0, 12.0; 8.0, 16.0],
+            nalgebra::matrix![-2.0, -6.0; -4.0, -8.0],
+            nalgebra::matrix![6.0, 2.0; 8.0, 4.0],
+            nalgebra::matrix![6.0, 2.0; 8.0, 4.0],
+        ];
+
+        let res = exec(json.as_bytes()).unwrap();
+        let mut work_reader = WorkReader::new(json.as_bytes());
+        for (res, &expected) in res.iter().zip(&expected) {
+            let res_streamingly = work_reader.next().await.unwrap().unwrap();
+            assert_eq!(res.matrix().unwrap().view(), expected);
+            assert_eq!(res_streamingly.matrix().unwrap().view(), expected);
+        }
+        assert!(work_reader.next().await.unwrap().is_none());
+
+        // Errors in nested operands are located within the expression
+        let json = r#"[{ "expr": { "code": "add", "lhs": { "d": [1], "n": 1 },
+            "rhs": { "code": "matmul", "lhs": { "d": [1, 2], "n": 1 }, "rhs": { "d": [1, 2], "n": 1 } }
+        } }]"#;
+        let (err, err_streamingly) = first_errors(json).await;
+        for err in [err, err_streamingly] {
+            assert_eq!(err.location().unwrap().json_path(), "$[0].expr.rhs");
+            assert!(matches!(err.kind(), StrompyError::ShapeMismatch { op: "matmul", .. }));
+        }
+
+        let json = r#"[{ "expr": { "d": [1], "n": 1 }, "op": [] }]"#;
+        let (err, err_streamingly) = first_errors(json).await;
+        assert!(err.to_string().contains(r#"Key "expr" cannot be combined with key "op""#));
+        assert!(matches!(
+            err_streamingly.kind(),
+            StrompyError::ConflictingKeys("expr", "op")
+        ));
+    }
+
+    #[tokio::test]
+    async fn it_evaluates_lhs_before_rhs_whatever_the_key_order() {
+        let json = r#"[
+            { "expr": { "code": "add", "rhs": { "ref": "s" }, "lhs": { "d": [1], "n": 1, "as": "s" } } },
+            { "op": [{ "code": "add", "rhs": { "code": "scale", "factor": 2, "lhs": { "ref": "t" } } }],
+              "lhs": { "d": [1], "n": 1, "as": "t" } }
+        ]"#;
+        let expected = [nalgebra::matrix![2.0], nalgebra::matrix![3.0]];
+
+        let res = exec(json.as_bytes()).unwrap();
+        let mut work_reader = WorkReader::new(json.as_bytes());
+        for (res, &expected) in res.iter().zip(&expected) {
+            let res_streamingly = work_reader.next().await.unwrap().unwrap();
+            assert_eq!(res.matrix().unwrap().view(), expected);
+            assert_eq!(res_streamingly.matrix().unwrap().view(), expected);
+        }
+        assert!(validate(json.as_bytes()).is_empty());
+
+        // Names defined in the `rhs` cannot be referred to from the `lhs`,
+        // even if the `rhs` comes first
+        let cases = [
+            (
+                r#"[{ "op": [{ "code": "add", "rhs": { "d": [1], "n": 1, "as": "s" } }], "lhs": { "ref": "s" } }]"#,
+                "$[0].lhs",
+            ),
+            (
+                r#"[{ "expr": { "code": "add", "lhs": { "ref": "s" }, "rhs": { "d": [1], "n": 1, "as": "s" } } }]"#,
+                "$[0].expr.lhs",
+            ),
+        ];
+        for (json, path) in cases {
+            let (err, err_streamingly) = first_errors(json).await;
+            let problems = validate(json.as_bytes());
+            assert_eq!(problems.len(), 1);
+            for err in [&err, &err_streamingly, &problems[0]] {
+                assert_eq!(err.location().unwrap().json_path(), path);
+                assert!(matches!(err.kind(), StrompyError::DanglingRef(name) if name == "s"));
+            }
+        }
+
+        // Errors in a `rhs` that is evaluated after the fact are still located within it
+        let json = r#"[{ "op": [{ "code": "add",
+            "rhs": { "code": "matmul", "lhs": { "d": [1, 2], "n": 1 }, "rhs": { "d": [1, 2], "n": 1 } }
+        }], "lhs": { "d": [1], "n": 1 } }]"#;
+        let (err, err_streamingly) = first_errors(json).await;
+        for err in [err, err_streamingly] {
+            assert_eq!(err.location().unwrap().json_path(), "$[0].op[0].rhs");
+            assert!(matches!(
+                err.kind(),
+                StrompyError::ShapeMismatch { op: "matmul", .. }
+            ));
+        }
+    }
+
+    #[tokio::test]
+    async fn it_solves_and_decomposes() {
+        let json = r#"[
+            { "lhs": { "d": [4, 2, 7, 6], "n": 2 }, "op": [{ "code": "inverse" }], "as": "a" },
+            { "lhs": { "d": [4, 2, 7, 6], "n": 2 }, "op": [{ "code": "determinant" }] },
+            { "lhs": { "d": [4, 2, 7, 6], "n": 2 }, "op": [{ "code": "solve", "rhs": { "d": [1, 2], "n": 1 } }] },
+            { "lhs": { "d": [4, 2, 2, 3], "n": 2 }, "op": [{ "code": "cholesky" }] },
+            { "lhs": { "d": [1, 4, 2, 5, 3, 6], "n": 3 }, "op": [{ "code": "qr" }], "as": "f" },
+            { "lhs": { "ref": "f.q" }, "op": [{ "code": "transpose" }, { "code": "matmul", "rhs": { "ref": "f.q" } }] }
+        ]"#;
+        let a = nalgebra::dmatrix![4.0, 7.0; 2.0, 6.0];
+        let expected = [
+            nalgebra::dmatrix![0.6, -0.7; -0.2, 0.4],
+            nalgebra::dmatrix![10.0],
+            nalgebra::dmatrix![-0.8; 0.6],
+            nalgebra::dmatrix![2.0, 0.0; 1.0, 2.0_f64.sqrt()],
+        ];
+
+        let res = exec(json.as_bytes()).unwrap();
+        let mut work_reader = WorkReader::new(json.as_bytes());
+        for (res, expected) in res.iter().zip(&expected) {
+            let res_streamingly = work_reader.next().await.unwrap().unwrap();
+            for res in [res, &res_streamingly] {
+                let diff = res.matrix().unwrap().view() - expected;
+                assert!(diff.amax() < 1e-12);
+            }
+        }
+
+        // The factors multiply back to the input
+        let WorkResult::Factors(qr) = &res[4] else {
+            panic!("qr did not result in factors");
+        };
+        assert_eq!(qr.op(), "qr");
+        let [q, r] = ["q", "r"].map(|name| qr.get(name).unwrap().view());
+        let input = nalgebra::matrix![1.0, 2.0, 3.0; 4.0, 5.0, 6.0];
+        assert!((q * r - input).amax() < 1e-12);
+        // Each factor can be referred to by name
+        let identity = res[5].matrix().unwrap().view() - nalgebra::Matrix2::identity();
+        assert!(identity.amax() < 1e-12);
+
+        for (code, names) in [("lu", ["p", "l", "u"]), ("svd", ["u", "s", "v_t"])] {
+            let json = format!(
+                r#"{{ "lhs": {{ "d": [4, 2, 7, 6], "n": 2 }}, "op": [{{ "code": "{code}" }}] }}"#
+            );
+            let work: PieceOfWork = serde_json::from_str(&json).unwrap();
+            let WorkResult::Factors(factors) = work.exec().unwrap() else {
+                panic!("{code} did not result in factors");
+            };
+            let [x, y, z] = names.map(|name| factors.get(name).unwrap().view());
+            let product = match code {
+                "lu" => x * &a - y * z,
+                _ => x * nalgebra::DMatrix::from_diagonal(&y.column(0)) * z - &a,
+            };
+            assert!(product.amax() < 1e-12);
+            assert_eq!(
+                factors.iter().map(|(name, _)| name).collect::<Vec<_>>(),
+                names
+            );
+        }
+
+        let json =
+            r#"[{ "lhs": { "d": [1, 2, 3, 4, 5, 6], "n": 3 }, "op": [{ "code": "inverse" }] }]"#;
+        let (err, err_streamingly) = first_errors(json).await;
+        for err in [err, err_streamingly] {
+            assert_eq!(err.location().unwrap().json_path(), "$[0].op[0]");
+            assert!(matches!(
+                err.kind(),
+                StrompyError::NotSquare { op: "inverse", .. }
+            ));
+        }
+
+        let json = r#"[{ "lhs": { "d": [1, 2, 2, 4], "n": 2 }, "op": [{ "code": "inverse" }] }]"#;
+        let (err, err_streamingly) = first_errors(json).await;
+        for err in [err, err_streamingly] {
+            assert!(matches!(
+                err.kind(),
+                StrompyError::Singular { op: "inverse" }
+            ));
+        }
+
+        // Decompositions cannot be followed by other operations
+        let json = r#"[{ "lhs": { "d": [4, 2, 7, 6], "n": 2 }, "op": [{ "code": "lu" }, { "code": "transpose" }] }]"#;
+        let (err, err_streamingly) = first_errors(json).await;
+        for err in [err, err_streamingly] {
+            assert_eq!(err.location().unwrap().json_path(), "$[0].op[1]");
+            assert!(matches!(err.kind(), StrompyError::NotAMatrix { op: "lu" }));
+        }
+
+        for code in ["svd", "determinant", "cholesky"] {
+            let json =
+                format!(r#"[{{ "lhs": {{ "d": [], "n": 1 }}, "op": [{{ "code": "{code}" }}] }}]"#);
+            let (err, err_streamingly) = first_errors(&json).await;
+            for err in [err, err_streamingly] {
+                assert_eq!(err.location().unwrap().json_path(), "$[0].lhs");
+                assert!(matches!(
+                    err.kind(),
+                    StrompyError::InvalidShape { len: 0, n: 1 }
+                ));
+            }
+        }
+    }
+
+    #[tokio::test]
+    async fn it_maps_and_reduces() {
+        // [[1, 2, -3], [-4, 5, 6]]
+        let lhs = r#"{ "d": [1, -4, 2, 5, -3, 6], "n": 3 }"#;
+        let cases = [
+            (
+                r#"[{ "code": "abs" }, { "code": "sum", "axis": "rows" }]"#,
+                nalgebra::dmatrix![6.0; 15.0],
+            ),
+            (
+                r#"[{ "code": "clamp", "min": -1, "max": 4 }, { "code": "max", "axis": "cols" }]"#,
+                nalgebra::dmatrix![1.0, 4.0, 4.0],
+            ),
+            (
+                r#"[{ "code": "pow", "exponent": 2 }, { "code": "sqrt" }, { "code": "min" }]"#,
+                nalgebra::dmatrix![1.0],
+            ),
+            (r#"[{ "code": "min" }]"#, nalgebra::dmatrix![-4.0]),
+            (r#"[{ "code": "mean" }]"#, nalgebra::dmatrix![7.0 / 6.0]),
+            (
+                r#"[{ "code": "exp" }, { "code": "log" }, { "code": "mean", "axis": "cols" }]"#,
+                nalgebra::dmatrix![-1.5, 3.5, 1.5],
+            ),
+            (
+                r#"[{ "code": "norm" }]"#,
+                nalgebra::dmatrix![91.0_f64.sqrt()],
+            ),
+            (
+                r#"[{ "code": "norm", "kind": "l1", "axis": "cols" }]"#,
+                nalgebra::dmatrix![5.0, 7.0, 9.0],
+            ),
+            (
+                r#"[{ "axis": "rows", "kind": "inf", "code": "norm" }]"#,
+                nalgebra::dmatrix![3.0; 6.0],
+            ),
+        ];
+        for (op, expected) in cases {
+            let json = format!(r#"[{{ "lhs": {lhs}, "op": {op} }}]"#);
+            let (res, res_streamingly) = exec_both(&json).await;
+            for res in [res, res_streamingly] {
+                let diff = res.view() - &expected;
+                assert!(diff.amax() < 1e-12, "{op} resulted in {:?}", res.view());
+            }
+        }
+
+        // Parameters can be given in expressions as well
+        let json = r#"[{ "expr": { "code": "norm", "kind": "l1", "axis": "cols",
+            "lhs": { "code": "clamp", "max": 0, "lhs": { "d": [1, -4, 2, 5, -3, 6], "n": 3 } }
+        } }]"#;
+        let (res, res_streamingly) = exec_both(json).await;
+        for res in [res, res_streamingly] {
+            assert_eq!(res.view(), nalgebra::matrix![4.0, 0.0, 3.0]);
+        }
+
+        let json =
+            r#"[{ "lhs": { "d": [1], "n": 1 }, "op": [{ "code": "sum", "axis": "diagonal" }] }]"#;
+        let (err, err_streamingly) = first_errors(json).await;
+        assert!(err
+            .to_string()
+            .contains(r#"Invalid value "diagonal" for key "axis""#));
+        assert!(matches!(
+            err_streamingly.kind(),
+            StrompyError::InvalidValue { key: "axis", value } if value == "diagonal"
+        ));
+
+        let json = r#"[{ "lhs": { "d": [1], "n": 1 }, "op": [{ "code": "pow" }] }]"#;
+        let (_, err_streamingly) = first_errors(json).await;
+        assert!(matches!(
+            err_streamingly.kind(),
+            StrompyError::MissingKey("exponent")
+        ));
+
+        let json = r#"[{ "lhs": { "ref": "a", "axis": "rows" }, "op": [] }]"#;
+        let (_, err_streamingly) = first_errors(json).await;
+        assert!(matches!(
+            err_streamingly.kind(),
+            StrompyError::ConflictingKeys("ref", "axis")
+        ));
+    }
+
+    #[tokio::test]
+    async fn it_slices_reshapes_and_stacks() {
+        // [[1, 2, 3], [4, 5, 6], [7, 8, 9], [10, 11, 12]]
+        let lhs = r#"{ "d": [1, 4, 7, 10, 2, 5, 8, 11, 3, 6, 9, 12], "n": 3 }"#;
+        let cases = [
+            (
+                r#"[{ "code": "slice", "rows": { "step": 2 }, "cols": { "start": 1 } }]"#,
+                nalgebra::dmatrix![2.0, 3.0; 8.0, 9.0],
+            ),
+            (
+                r#"[{ "code": "slice", "rows": { "start": 3, "stop": 4 } }]"#,
+                nalgebra::dmatrix![10.0, 11.0, 12.0],
+            ),
+            (
+                r#"[{ "code": "reshape", "shape": [2, 6] }]"#,
+                nalgebra::dmatrix![
+                    1.0, 7.0, 2.0, 8.0, 3.0, 9.0;
+                    4.0, 10.0, 5.0, 11.0, 6.0, 12.0
+                ],
+            ),
+            (
+                r#"[{ "code": "hstack", "rhs": { "d": [0, 0, 0, 0], "n": 1 } }]"#,
+                nalgebra::dmatrix![
+                    1.0, 2.0, 3.0, 0.0;
+                    4.0, 5.0, 6.0, 0.0;
+                    7.0, 8.0, 9.0, 0.0;
+                    10.0, 11.0, 12.0, 0.0
+                ],
+            ),
+            (
+                r#"[{ "code": "vstack", "rhs": { "d": [-1, -2, -3], "n": 3 } }]"#,
+                nalgebra::dmatrix![
+                    1.0, 2.0, 3.0;
+                    4.0, 5.0, 6.0;
+                    7.0, 8.0, 9.0;
+                    10.0, 11.0, 12.0;
+                    -1.0, -2.0, -3.0
+                ],
+            ),
+            (r#"[{ "code": "diag" }]"#, nalgebra::dmatrix![1.0; 5.0; 9.0]),
+            (
+                r#"[{ "code": "slice", "cols": { "stop": 1 } }, { "code": "diag" }]"#,
+                nalgebra::DMatrix::from_diagonal(&nalgebra::dvector![1.0, 4.0, 7.0, 10.0]),
+            ),
+        ];
+        for (op, expected) in cases {
+            let json = format!(r#"[{{ "lhs": {lhs}, "op": {op} }}]"#);
+            let (res, res_streamingly) = exec_both(&json).await;
+            assert_eq!(res.view(), expected);
+            assert_eq!(res_streamingly.view(), expected);
+        }
+
+        // The right-hand side of a concatenation can be a reference
+        let json = r#"[
+            { "lhs": { "d": [1, 2], "n": 1 }, "op": [], "as": "a" },
+            { "lhs": { "ref": "a" }, "op": [
+                { "code": "hstack", "rhs": { "ref": "a" } },
+                { "code": "hstack", "rhs": { "ref": "a" } }
+            ] }
+        ]"#;
+        let res = exec(json.as_bytes()).unwrap();
+        let mut work_reader = WorkReader::new(json.as_bytes());
+        work_reader.next().await.unwrap().unwrap();
+        let res_streamingly = work_reader.next().await.unwrap().unwrap();
+        let expected = nalgebra::matrix![1.0, 1.0, 1.0; 2.0, 2.0, 2.0];
+        assert_eq!(res[1].matrix().unwrap().view(), expected);
+        assert_eq!(res_streamingly.matrix().unwrap().view(), expected);
+
+        let errors = [
+            r#"{ "code": "slice", "rows": { "start": 2, "stop": 5 } }"#,
+            r#"{ "code": "slice", "cols": { "step": 0 } }"#,
+            r#"{ "code": "reshape", "shape": [5, 2] }"#,
+            // 4611686018427387907 * 4 overflows to the 12 elements of `lhs`
+            r#"{ "code": "reshape", "shape": [4611686018427387907, 4] }"#,
+            r#"{ "code": "vstack", "rhs": { "d": [1, 2], "n": 2 } }"#,
+        ];
+        for op in errors {
+            let json = format!(r#"[{{ "lhs": {lhs}, "op": [{op}] }}]"#);
+            let (err, err_streamingly) = first_errors(&json).await;
+            assert_eq!(err.kind().to_string(), err_streamingly.kind().to_string());
+            assert_eq!(err.location().unwrap().json_path(), "$[0].op[0]");
+        }
+        let json = format!(r#"[{{ "lhs": {lhs}, "op": [{}] }}]"#, errors[0]);
+        let (err, _) = first_errors(&json).await;
+        assert!(matches!(
+            err.kind(),
+            StrompyError::InvalidRange {
+                axis: "rows",
+                start: 2,
+                stop: 5,
+                len: 4
+            }
+        ));
+    }
+
+    #[tokio::test]
+    async fn it_reads_data_in_either_order() {
+        let cases = [
+            (
+                r#"{ "d": [1, 2, 3, 4, 5, 6], "n": 3, "order": "row" }"#,
+                nalgebra::matrix![1.0, 2.0, 3.0; 4.0, 5.0, 6.0],
+            ),
+            (
+                r#"{ "d": [1, 2, 3, 4, 5, 6], "n": 3, "order": "col" }"#,
+                nalgebra::matrix![1.0, 3.0, 5.0; 2.0, 4.0, 6.0],
+            ),
+            (
+                r#"{ "d": [1, 2, 3, 4, 5, 6], "n": 3 }"#,
+                nalgebra::matrix![1.0, 3.0, 5.0; 2.0, 4.0, 6.0],
+            ),
+        ];
+        for (lhs, expected) in cases {
+            let json = format!(r#"[{{ "lhs": {lhs}, "op": [] }}]"#);
+            let (res, res_streamingly) = exec_both(&json).await;
+            assert_eq!(res.view(), expected);
+            assert_eq!(res_streamingly.view(), expected);
+        }
+
+        // Operands can mix orders
+        let json = r#"[{
+            "lhs": { "d": [1, 2, 3, 4, 5, 6], "n": 3, "order": "row" },
+            "op": [{ "code": "matmul", "rhs": { "d": [1, 0, -1], "n": 1, "order": "row" } }]
+        }]"#;
+        let (res, res_streamingly) = exec_both(json).await;
+        for res in [res, res_streamingly] {
+            assert_eq!(res.view(), nalgebra::matrix![-2.0; -2.0]);
+        }
+
+        // Written results are read back in with the same shape
+        let json = r#"[{ "lhs": { "d": [1, 2, 3, 4, 5, 6], "n": 2, "order": "row" }, "op": [] }]"#;
+        let mut work_reader = WorkReader::new(json.as_bytes());
+        let mut out = Vec::new();
+        let mut work_writer = WorkWriter::new(&mut out);
+        while let Some(res) = work_reader.next().await.unwrap() {
+            work_writer.write(&res).await.unwrap();
+        }
+        work_writer.finish().await.unwrap();
+        let [res]: [MatrixBuf; 1] = serde_json::from_slice(&out).unwrap();
+        assert_eq!(res.view(), nalgebra::matrix![1.0, 2.0; 3.0, 4.0; 5.0, 6.0]);
+
+        let json = r#"[{ "lhs": { "d": [1, 2], "n": 2, "order": "diagonal" }, "op": [] }]"#;
+        let (err, err_streamingly) = first_errors(json).await;
+        assert!(err
+            .to_string()
+            .contains(r#"Invalid value "diagonal" for key "order""#));
+        assert!(matches!(
+            err_streamingly.kind(),
+            StrompyError::InvalidValue { key: "order", value } if value == "diagonal"
+        ));
+
+        let json = r#"[{ "lhs": { "d": [1, 2, 3], "n": 2, "order": "row" }, "op": [] }]"#;
+        let (_, err_streamingly) = first_errors(json).await;
+        assert!(matches!(
+            err_streamingly.kind(),
+            StrompyError::InvalidShape { len: 3, n: 2 }
+        ));
+
+        let json = r#"[{ "lhs": { "ref": "a", "order": "row" }, "op": [] }]"#;
+        let (_, err_streamingly) = first_errors(json).await;
+        assert!(matches!(
+            err_streamingly.kind(),
+            StrompyError::ConflictingKeys("ref", "order")
+        ));
+        let problems = validate(json.as_bytes());
+        assert!(matches!(
+            problems[0].kind(),
+            StrompyError::ConflictingKeys("ref", "order")
+        ));
+    }
+
+    #[test]
+    fn it_executes_in_parallel_in_input_order() {
+        let piece = |x: usize| format!(r#"{{ "lhs": {{ "d": [{x}], "n": 1 }}, "op": [] }}"#);
+        let pieces: Vec<_> = (0..1000).map(piece).collect();
+        let json = format!("[{}]", pieces.join(","));
+
+        let pool = rayon::ThreadPoolBuilder::new()
+            .num_threads(4)
+            .build()
+            .unwrap();
+        let res = pool.install(|| exec(json.as_bytes())).unwrap();
+        for (x, res) in res.iter().enumerate() {
+            assert_eq!(res.matrix().unwrap().view(), nalgebra::matrix![x as f64]);
+        }
+
+        // The error of the first failing piece is returned
+        let invalid = r#"{ "lhs": { "d": [1], "n": 1 }, "op": [{ "code": "add", "rhs": { "d": [1, 2], "n": 2 } }] }"#;
+        let mut pieces = pieces;
+        pieces[400] = invalid.to_owned();
+        pieces[700] = invalid.to_owned();
+        let json = format!("[{}]", pieces.join(","));
+        let err = pool.install(|| exec(json.as_bytes())).unwrap_err();
+        assert_eq!(err.location().unwrap().json_path(), "$[400].op[0]");
+    }
+
+    #[tokio::test]
+    async fn it_continues_after_errors() {
+        let json = r#"[
+            { "lhs": { "d": [1], "n": 1 }, "op": [] },
+            { "lhs": { "d": "x", "n": 1 }, "op": [{ "code": "scale", "factor": 2 }] },
+            { "lhs": { "d": [2], "n": 1 }, "op": [{ "code": "frobnicate" }, { "code": "transpose" }] },
+            { "lhs": { "d": [3], "n": 1 }, "op": [{ "code": "add", "rhs": { "ref": "a" } }] },
+            5,
+            { "lhs": { "d": [4], "n": 1 }, "op": [], "as": "a" },
+            { "lhs": { "ref": "a" }, "op": [{ "code": "scale", "factor": 2 }] }
+        ]"#;
+        let ndjson: String = serde_json::from_str::<Vec<serde_json::Value>>(json)
+            .unwrap()
+            .iter()
+            .map(|piece| format!("{piece}\n"))
+            .collect();
+
+        let mut readers = [
+            WorkReader::new(json.as_bytes()),
+            WorkReader::with_mode(ndjson.as_bytes(), InputMode::Values),
+        ];
+        async fn next(work_reader: &mut WorkReader<&[u8]>) -> Result<MatrixBuf, ItemError> {
+            let res = work_reader.next_item().await.unwrap().unwrap();
+            res.map(|res| res.into_matrix().unwrap())
+        }
+
+        for work_reader in &mut readers {
+            assert_eq!(next(work_reader).await.unwrap().view(), nalgebra::matrix![1.0]);
+            for index in 1..5 {
+                assert_eq!(next(work_reader).await.unwrap_err().index, index);
+            }
+            assert_eq!(next(work_reader).await.unwrap().view(), nalgebra::matrix![4.0]);
+            assert_eq!(next(work_reader).await.unwrap().view(), nalgebra::matrix![8.0]);
+            assert!(work_reader.next_item().await.unwrap().is_none());
+        }
+
+        let mut work_reader = WorkReader::new(json.as_bytes());
+        work_reader.next_item().await.unwrap().unwrap().unwrap();
+        let err = work_reader.next_item().await.unwrap().unwrap().unwrap_err();
+        assert_eq!(err.error.location().unwrap().json_path(), "$[1].lhs.d");
+        let err = work_reader.next_item().await.unwrap().unwrap().unwrap_err();
+        assert!(matches!(
+            err.error.kind(),
+            StrompyError::UnknownOperation(code) if code == "frobnicate"
+        ));
+
+        // Malformed input still ends reading
+        let json = r#"[{ "lhs": { "d": [1], "n": 1 }, "op": [] }, { "lhs": ] }]"#;
+        let mut work_reader = WorkReader::new(json.as_bytes());
+        work_reader.next_item().await.unwrap().unwrap().unwrap();
+        assert!(work_reader.next_item().await.is_err());
+        assert!(matches!(
+            work_reader.next_item().await,
+            Err(StrompyError::Poisoned)
+        ));
+
+        // Binary input continues with the next item as well
+        let work: Vec<serde_json::Value> = serde_json::from_str(
+            r#"[
+                { "lhs": { "d": [1, 2], "n": 3 }, "op": [] },
+                { "lhs": { "d": [1, 2], "n": 2 }, "op": [] }
+            ]"#,
+        )
+        .unwrap();
+        let mut cbor = Vec::new();
+        ciborium::into_writer(&work, &mut cbor).unwrap();
+        let mut work_reader = BinaryWorkReader::new(&cbor[..], BinaryFormat::Cbor, InputMode::Array);
+        let err = work_reader.next_item().await.unwrap().unwrap().unwrap_err();
+        assert_eq!(err.index, 0);
+        assert!(err.error.to_string().contains("cannot divide 2 elements into rows of 3 columns"));
+        let res = work_reader.next_item().await.unwrap().unwrap().unwrap();
+        assert_eq!(res.matrix().unwrap().view(), nalgebra::matrix![1.0, 2.0]);
+        assert!(work_reader.next_item().await.unwrap().is_none());
+
+        // Results named within work that fails are not kept
+        let json = r#"[
+            { "expr": { "code": "inverse", "lhs": { "d": [1, 2, 2, 4], "n": 2, "as": "m" } } },
+            { "lhs": { "ref": "m" }, "op": [] }
+        ]"#;
+        let work: Vec<serde_json::Value> = serde_json::from_str(json).unwrap();
+        let mut cbor = Vec::new();
+        ciborium::into_writer(&work, &mut cbor).unwrap();
+        let mut work_reader = WorkReader::new(json.as_bytes());
+        let mut cbor_reader =
+            BinaryWorkReader::new(&cbor[..], BinaryFormat::Cbor, InputMode::Array);
+        for _ in 0..2 {
+            let errors = [
+                work_reader.next_item().await.unwrap().unwrap().unwrap_err(),
+                cbor_reader.next_item().await.unwrap().unwrap().unwrap_err(),
+            ];
+            for err in errors {
+                match err.index {
+                    0 => assert!(matches!(
+                        err.error.kind(),
+                        StrompyError::Singular { op: "inverse" }
+                    )),
+                    _ => assert!(matches!(
+                        err.error.kind(),
+                        StrompyError::DanglingRef(name) if name == "m"
+                    )),
+                }
+            }
+        }
+    }
+
+    #[tokio::test]
+    async fn it_validates_without_executing() {
+        let json = r#"[
+            { "lhs": { "d": [1, 2, 3, 4], "n": 2 }, "op": [{ "code": "matmul", "rhs": { "d": [1, 2, 3], "n": 3 } }], "as": "a" },
+            { "lhs": { "ref": "a" }, "op": [{ "code": "frobnicate" }] },
+            { "lhs": { "ref": "b" }, "op": [{ "code": "scale", "factor": "x" }] },
+            { "expr": { "code": "transpose", "lhs": { "d": [1], "n": 1 } }, "op": [] },
+            { "lhs": { "d": [1, 2, 3], "n": 2 }, "op": [] },
+            { "expr": { "code": "transpose", "lhs": { "d": [1, 2, 3], "n": 3 } }, "as": "t" },
+            { "expr": { "code": "matmul", "lhs": { "ref": "t" }, "rhs": { "d": [1, 2, 3], "n": 3 } } },
+            5,
+            { "op": [] }
+        ]"#;
+        let expected = [
+            "$[0].op[0]",
+            "$[1].op[0]",
+            "$[2].lhs",
+            "$[2].op[0].factor",
+            "$[3]",
+            "$[4].lhs",
+            "$[7]",
+            "$[8]",
+        ];
+        let paths = |problems: &[StrompyError]| -> Vec<String> {
+            problems
+                .iter()
+                .map(|e| e.location().unwrap().json_path())
+                .collect()
+        };
+
+        let problems = validate(json.as_bytes());
+        assert_eq!(paths(&problems), expected);
+        assert!(matches!(
+            problems[0].kind(),
+            StrompyError::ShapeMismatch { op: "matmul", .. }
+        ));
+        assert!(matches!(
+            problems[1].kind(),
+            StrompyError::UnknownOperation(code) if code == "frobnicate"
+        ));
+        assert!(matches!(problems[2].kind(), StrompyError::DanglingRef(name) if name == "b"));
+        assert!(matches!(problems[3].kind(), StrompyError::Serde(_)));
+        assert!(matches!(
+            problems[4].kind(),
+            StrompyError::ConflictingKeys("expr", "op")
+        ));
+        assert!(matches!(
+            problems[5].kind(),
+            StrompyError::InvalidShape { len: 3, n: 2 }
+        ));
+        assert!(matches!(
+            problems[7].kind(),
+            StrompyError::MissingKey("lhs")
+        ));
+
+        // Valid work has no problems, and nothing is executed
+        let valid = r#"[{ "lhs": { "d": [1, 2], "n": 2 }, "op": [{ "code": "dot", "rhs": { "d": [3, 4], "n": 2 } }] }]"#;
+        assert!(validate(valid.as_bytes()).is_empty());
+
+        // The factors of a decomposition are checked one by one
+        let decomposed = r#"[
+            { "lhs": { "d": [1, 2, 3, 4, 5, 6], "n": 3 }, "op": [{ "code": "qr" }], "as": "f" },
+            { "lhs": { "ref": "f.r" }, "op": [{ "code": "matmul", "rhs": { "ref": "f.q" } }] },
+            { "lhs": { "ref": "f" }, "op": [] },
+            { "lhs": { "d": [1, 2, 3], "n": 3 }, "op": [{ "code": "inverse" }] },
+            { "lhs": { "d": [1, 2, 3, 4], "n": 2 }, "op": [{ "code": "lu" }, { "code": "transpose" }] }
+        ]"#;
+        let problems = validate(decomposed.as_bytes());
+        assert_eq!(
+            paths(&problems),
+            ["$[1].op[0]", "$[2].lhs", "$[3].op[0]", "$[4].op[1]"]
+        );
+        assert!(matches!(
+            problems[0].kind(),
+            StrompyError::ShapeMismatch { op: "matmul", .. }
+        ));
+        assert!(matches!(problems[1].kind(), StrompyError::DanglingRef(name) if name == "f"));
+        assert!(matches!(
+            problems[2].kind(),
+            StrompyError::NotSquare { op: "inverse", .. }
+        ));
+        assert!(matches!(
+            problems[3].kind(),
+            StrompyError::NotAMatrix { op: "lu" }
+        ));
+
+        // Reductions change the shape, and their parameters are checked as well
+        let reduced = r#"[{ "lhs": { "d": [1, 2], "n": 2 }, "op": [
+            { "code": "sum", "axis": "cols" },
+            { "code": "dot", "rhs": { "d": [1], "n": 1 } },
+            { "code": "norm", "kind": "l3" }
+        ] }]"#;
+        let problems = validate(reduced.as_bytes());
+        assert_eq!(paths(&problems), ["$[0].op[1]", "$[0].op[2].kind"]);
+        assert!(problems[1]
+            .to_string()
+            .contains(r#"Invalid value "l3" for key "kind""#));
+
+        // Streaming and binary input report the same problems
+        let work: Vec<serde_json::Value> = serde_json::from_str(json).unwrap();
+        let ndjson: String = work.iter().map(|piece| format!("{piece}\n")).collect();
+        let problems = WorkReader::new(json.as_bytes()).validate().await;
+        assert_eq!(paths(&problems), expected);
+        let problems = WorkReader::with_mode(ndjson.as_bytes(), InputMode::Values)
+            .validate()
+            .await;
+        assert_eq!(paths(&problems), expected);
+        let mut cbor = Vec::new();
+        ciborium::into_writer(&work, &mut cbor).unwrap();
+        assert_eq!(paths(&validate_binary(&cbor, BinaryFormat::Cbor)), expected);
+        let problems = BinaryWorkReader::new(&cbor[..], BinaryFormat::Cbor, InputMode::Array)
+            .validate()
+            .await;
+        assert_eq!(paths(&problems), expected);
+
+        // Keys of the input format occur only once, and nesting is limited
+        let duplicate = r#"[{
+            "lhs": { "d": [1], "n": 1 },
+            "lhs": { "d": [1], "n": 1 },
+            "op": [{ "code": "scale", "factor": 2, "factor": 3 }],
+            "x": 1,
+            "x": 2
+        }]"#;
+        for problems in [
+            validate(duplicate.as_bytes()),
+            WorkReader::new(duplicate.as_bytes()).validate().await,
+        ] {
+            assert_eq!(paths(&problems), ["$[0].lhs", "$[0].op[0].factor"]);
+            assert!(matches!(
+                problems[0].kind(),
+                StrompyError::DuplicateKey("lhs")
+            ));
+            assert!(matches!(
+                problems[1].kind(),
+                StrompyError::DuplicateKey("factor")
+            ));
+        }
+        let nested = format!("[{}{}]", "[".repeat(100_000), "]".repeat(100_000));
+        let problems = WorkReader::new(nested.as_bytes()).validate().await;
+        assert_eq!(problems.len(), 1);
+        assert!(matches!(problems[0].kind(), StrompyError::TooDeep { .. }));
+        assert!(matches!(
+            validate(nested.as_bytes())[0].kind(),
+            StrompyError::Serde(_)
+        ));
+
+        // Shapes are derived without overflowing, however large they grow
+        let mut work =
+            vec![serde_json::json!({ "lhs": { "d": [1], "n": 1 }, "op": [], "as": "x" })];
+        let double = serde_json::json!({
+            "lhs": { "ref": "x" },
+            "op": [{ "code": "hstack", "rhs": { "ref": "x" } }],
+            "as": "x"
+        });
+        work.extend(vec![double; 64]);
+        let problems = validate(serde_json::to_string(&work).unwrap().as_bytes());
+        if cfg!(feature = "heap") {
+            assert_eq!(paths(&problems), ["$[64].op[0]"]);
+            assert!(matches!(
+                problems[0].kind(),
+                StrompyError::ShapeOverflow { op: "hstack" }
+            ));
+        } else {
+            // The first result of 64 elements exceeds the capacity already
+            assert_eq!(paths(&problems), ["$[6].op[0]"]);
+            assert!(matches!(problems[0].kind(), StrompyError::Capacity { .. }));
+        }
+
+        // Results, factors included, have to fit in the storage capacity like the input does
+        let large = r#"[
+            { "lhs": { "d": [1, 2, 3, 4, 5, 6, 7], "n": 1 },
+              "op": [{ "code": "matmul", "rhs": { "d": [1, 2, 3, 4, 5, 6, 7], "n": 7 } }] },
+            { "lhs": { "d": [1, 2, 3, 4, 5, 6, 7], "n": 1 }, "op": [{ "code": "lu" }] }
+        ]"#;
+        let problems = validate(large.as_bytes());
+        if cfg!(feature = "heap") {
+            assert!(problems.is_empty());
+        } else {
+            assert_eq!(paths(&problems), ["$[0].op[0]", "$[1].op[0]"]);
+            let err = exec(large.as_bytes()).unwrap_err();
+            for e in problems.iter().chain([&err]) {
+                assert!(matches!(
+                    e.kind(),
+                    StrompyError::Capacity {
+                        capacity: MATRIX_CAPACITY
+                    }
+                ));
+            }
+        }
+
+        // Malformed input ends validation, after the problems found before it
+        let json = r#"[{ "lhs": { "ref": "x" }, "op": [] }, { "lhs": ] }]"#;
+        let problems = WorkReader::new(json.as_bytes()).validate().await;
+        assert_eq!(problems.len(), 2);
+        assert!(matches!(problems[0].kind(), StrompyError::DanglingRef(_)));
+        assert!(matches!(problems[1].kind(), StrompyError::Struson(_)));
+        assert_eq!(validate(json.as_bytes()).len(), 1);
+    }
+
+    #[tokio::test]
+    async fn it_reads_binary_formats() {
+        use futures::{stream, TryStreamExt};
+        use serde_json::Value;
+
+        fn encode(format: BinaryFormat, value: &Value) -> Vec<u8> {
+            match format {
+                BinaryFormat::Cbor => {
+                    let mut bytes = Vec::new();
+                    ciborium::into_writer(value, &mut bytes).unwrap();
+                    bytes
+                }
+                BinaryFormat::MessagePack => rmp_serde::to_vec(value).unwrap(),
+            }
+        }
+
+        let json = r#"[
+            { "lhs": { "d": [1, 2, 3, 4, 5, 6], "n": 3 }, "op": [{ "code": "transpose" }] },
+            { "lhs": { "d": [1.5, -2, 0.25, 4], "n": 2 }, "op": [{ "code": "scale", "factor": 2 }] }
+        ]"#;
+        let expected = exec(json.as_bytes()).unwrap();
+        let work: Vec<Value> = serde_json::from_str(json).unwrap();
+
+        for format in [BinaryFormat::Cbor, BinaryFormat::MessagePack] {
+            let array = encode(format, &Value::Array(work.clone()));
+            let values: Vec<u8> = work.iter().flat_map(|piece| encode(format, piece)).collect();
+
+            let res = exec_binary(&array, format).unwrap();
+            assert_eq!(res.len(), expected.len());
+            for (res, expected) in res.iter().zip(&expected) {
+                assert_eq!(
+                    res.matrix().unwrap().view(),
+                    expected.matrix().unwrap().view()
+                );
+            }
+
+            for (bytes, mode) in [(&array, InputMode::Array), (&values, InputMode::Values)] {
+                // Feed the input one byte at a time, splitting every item across reads
+                let reader = stream::iter(bytes.chunks(1).map(Ok::<_, std::io::Error>))
+                    .into_async_read();
+                let mut work_reader = BinaryWorkReader::new(reader, format, mode);
+                for expected in &expected {
+                    let res = work_reader.next().await.unwrap().unwrap();
+                    assert_eq!(
+                        res.matrix().unwrap().view(),
+                        expected.matrix().unwrap().view()
+                    );
+                }
+                assert!(work_reader.next().await.unwrap().is_none());
+            }
+
+            // Errors in the work itself are reported just like they are for JSON
+            let invalid = serde_json::json!([{ "lhs": { "d": [1, 2, 3], "n": 2 }, "op": [] }]);
+            let err = exec_binary(&encode(format, &invalid), format).unwrap_err();
+            assert_eq!(err.location().unwrap().json_path(), "$[0].lhs");
+            assert!(matches!(
+                err.kind(),
+                StrompyError::InvalidShape { len: 3, n: 2 }
+            ));
+
+            let truncated = &array[..array.len() - 1];
+            let mut work_reader = BinaryWorkReader::new(truncated, format, InputMode::Array);
+            assert!(work_reader.next().await.is_ok());
+            assert!(work_reader.next().await.is_err());
+        }
+
+        // CBOR arrays of indefinite length are terminated by a break marker
+        let mut indefinite = encode(BinaryFormat::Cbor, &Value::Array(work));
+        indefinite[0] = 0x9f;
+        indefinite.push(0xff);
+        let mut work_reader =
+            BinaryWorkReader::new(&indefinite[..], BinaryFormat::Cbor, InputMode::Array);
+        for expected in &expected {
+            let res = work_reader.next().await.unwrap().unwrap();
+            assert_eq!(
+                res.matrix().unwrap().view(),
+                expected.matrix().unwrap().view()
+            );
+        }
+        assert!(work_reader.next().await.unwrap().is_none());
+
+        // A length that does not fit in memory is an error, rather than overflowing
+        let oversized = [0x7b, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff];
+        let mut work_reader =
+            BinaryWorkReader::new(&oversized[..], BinaryFormat::Cbor, InputMode::Values);
+        let err = work_reader.next().await.unwrap_err();
+        assert_eq!(err.location().unwrap().offset, Some(0));
+        assert!(matches!(
+            err.kind(),
+            StrompyError::Cbor(ciborium::de::Error::Syntax(0))
+        ));
+    }
+
+    #[tokio::test]
+    async fn it_handles_matrices_beyond_heapless_capacity() {
+        let d: Vec<f64> = (0..64).map(f64::from).collect();
+        let json = serde_json::json!([{
+            "lhs": { "d": d, "n": 8 },
+            "op": [{ "code": "scale", "factor": 0.5 }]
+        }])
+        .to_string();
+
+        let work: Result<[PieceOfWork; 1], _> = serde_json::from_str(&json);
+        let mut json_reader = JsonStreamReader::new(json.as_bytes());
+        json_reader.begin_array().await.unwrap();
+        let res_streamingly = PieceOfWork::exec_streamingly(&mut json_reader).await;
+
+        if cfg!(feature = "heap") {
+            let [work] = work.unwrap();
+            let expected: Vec<f64> = d.iter().map(|x| x * 0.5).collect();
+            assert_eq!(
+                &work.exec().unwrap().into_matrix().unwrap().d[..],
+                &expected[..]
+            );
+            assert_eq!(
+                &res_streamingly.unwrap().into_matrix().unwrap().d[..],
+                &expected[..]
+            );
+        } else {
+            assert!(work.is_err());
+            assert!(matches!(
+                res_streamingly,
+                Err(StrompyError::Capacity {
+                    capacity: MATRIX_CAPACITY
+                })
+            ));
+        }
+    }
+
+    #[tokio::test]
+    async fn it_handles_long_operation_chains() {
+        let op: Vec<_> = (0..OP_CHAIN_CAPACITY + 1)
+            .map(|_| serde_json::json!({ "code": "scale", "factor": 2 }))
+            .collect();
+        let json = serde_json::json!([{
+            "lhs": { "d": [1], "n": 1 },
+            "op": op
+        }])
+        .to_string();
+
+        let res = exec(json.as_bytes());
+        let mut json_reader = JsonStreamReader::new(json.as_bytes());
+        json_reader.begin_array().await.unwrap();
+        let res_streamingly = PieceOfWork::exec_streamingly(&mut json_reader).await;
+
+        if cfg!(feature = "heap") {
+            let [res]: [WorkResult; 1] = res.unwrap().try_into().unwrap();
+            assert_eq!(res.into_matrix().unwrap().view(), nalgebra::matrix![64.0]);
+            assert_eq!(
+                res_streamingly.unwrap().into_matrix().unwrap().view(),
+                nalgebra::matrix![64.0]
+            );
+        } else {
+            let err = res.unwrap_err();
+            assert_eq!(err.location().unwrap().json_path(), "$[0].op");
+            assert!(matches!(
+                err.kind(),
+                StrompyError::Capacity {
+                    capacity: OP_CHAIN_CAPACITY
+                }
+            ));
+            assert!(matches!(
+                res_streamingly,
+                Err(StrompyError::Capacity {
+                    capacity: OP_CHAIN_CAPACITY
+                })
+            ));
+        }
+    }
 }
@@ -0,0 +1,120 @@
+//! Matrix inversion, decompositions and solving linear systems, built on
+//! [nalgebra::linalg]. The shapes of the operands are checked before any
+//! of these are called, see [Operation](crate::Operation).
+
+use nalgebra as na;
+
+use crate::{error::StrompyError, storage::Storage, Factors, MatrixBuf, StrompyResult};
+
+/// The maximum number of iterations of the singular value decomposition
+/// before it is considered not to converge
+const SVD_MAX_ITERATIONS: usize = 1000;
+
+impl MatrixBuf {
+    /// Create a [MatrixBuf] holding a copy of `m`
+    fn from_matrix<R: na::Dim, C: na::Dim, S: na::RawStorage<f64, R, C>>(
+        m: &na::Matrix<f64, R, C, S>,
+    ) -> StrompyResult<Self> {
+        // Both nalgebra and the view of a [MatrixBuf] store matrices column by column
+        let mut d = Storage::new();
+        for &x in m.iter() {
+            d.push(x)?;
+        }
+        Self::new(d, m.ncols())
+    }
+
+    /// An owned copy of the matrix, as nalgebra's decompositions need
+    fn to_owned_matrix(&self) -> na::DMatrix<f64> {
+        self.view().clone_owned()
+    }
+
+    pub(crate) fn inverse(&self) -> StrompyResult<Self> {
+        let inverse = self
+            .to_owned_matrix()
+            .try_inverse()
+            .ok_or(StrompyError::Singular { op: "inverse" })?;
+        Self::from_matrix(&inverse)
+    }
+
+    pub(crate) fn determinant(&self) -> StrompyResult<Self> {
+        let determinant = self.to_owned_matrix().determinant();
+        Self::new(Storage::from_slice(&[determinant])?, 1)
+    }
+
+    /// Solve `self·x = rhs` for `x`
+    pub(crate) fn solve(&self, rhs: &MatrixBuf) -> StrompyResult<Self> {
+        let x = self
+            .to_owned_matrix()
+            .lu()
+            .solve(&rhs.view())
+            .ok_or(StrompyError::Singular { op: "solve" })?;
+        Self::from_matrix(&x)
+    }
+
+    pub(crate) fn cholesky(&self) -> StrompyResult<Self> {
+        let cholesky = self
+            .to_owned_matrix()
+            .cholesky()
+            .ok_or(StrompyError::NotPositiveDefinite { op: "cholesky" })?;
+        Self::from_matrix(&cholesky.l())
+    }
+
+    pub(crate) fn lu(&self) -> StrompyResult<Factors> {
+        let (p, l, u) = self.to_owned_matrix().lu().unpack();
+        let rows = self.shape().rows;
+        let mut permutation = na::DMatrix::<f64>::identity(rows, rows);
+        p.permute_rows(&mut permutation);
+
+        Factors::new(
+            "lu",
+            [
+                ("p", Self::from_matrix(&permutation)?),
+                ("l", Self::from_matrix(&l)?),
+                ("u", Self::from_matrix(&u)?),
+            ],
+        )
+    }
+
+    pub(crate) fn qr(&self) -> StrompyResult<Factors> {
+        let (q, r) = self.to_owned_matrix().qr().unpack();
+
+        Factors::new(
+            "qr",
+            [("q", Self::from_matrix(&q)?), ("r", Self::from_matrix(&r)?)],
+        )
+    }
+
+    pub(crate) fn svd(&self) -> StrompyResult<Factors> {
+        let no_convergence = StrompyError::NoConvergence { op: "svd" };
+        let svd = self
+            .to_owned_matrix()
+            .try_svd(true, true, f64::EPSILON, SVD_MAX_ITERATIONS)
+            .ok_or(no_convergence)?;
+        let (Some(u), Some(v_t)) = (&svd.u, &svd.v_t) else {
+            unreachable!("both u and v_t are computed");
+        };
+
+        Factors::new(
+            "svd",
+            [
+                ("u", Self::from_matrix(u)?),
+                ("s", Self::from_matrix(&svd.singular_values)?),
+                ("v_t", Self::from_matrix(v_t)?),
+            ],
+        )
+    }
+}
+
+impl Factors {
+    /// Create the factors of the decomposition `op`
+    fn new<const N: usize>(
+        op: &'static str,
+        parts: [(&'static str, MatrixBuf); N],
+    ) -> StrompyResult<Self> {
+        let mut storage = Storage::new();
+        for part in parts {
+            storage.push(part)?;
+        }
+        Ok(Self { op, parts: storage })
+    }
+}
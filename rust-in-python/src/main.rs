@@ -0,0 +1,293 @@
+//! The `strompy` command-line binary. Reads work from standard input or from
+//! files, executes it streamingly and writes the results to standard output.
+//! Does not need a Python interpreter.
+
+use std::{
+    collections::VecDeque,
+    fmt::Display,
+    fs::File,
+    io::{self, BufReader, BufWriter, Read},
+    num::NonZeroUsize,
+    path::PathBuf,
+    process::ExitCode,
+    sync::{mpsc, Mutex, PoisonError},
+    thread,
+};
+
+use clap::{Parser, ValueEnum};
+use futures::{executor::block_on, io::AllowStdIo, AsyncRead, AsyncWrite};
+use strompy::{
+    BinaryFormat, BinaryWorkReader, InputMode, ItemError, OutputMode, StrompyError, WorkReader,
+    WorkResult, WorkWriter,
+};
+
+/// The number of results of an input that are buffered while
+/// the results of earlier inputs are still being written
+const RESULT_BUFFER: usize = 64;
+
+/// Execute strompy work, writing the results as JSON to standard output
+#[derive(Parser, Debug)]
+#[command(version, about)]
+struct Args {
+    /// Files to read work from, each of which is a stream of its own.
+    /// Reads from standard input if none are given, or for `-`.
+    inputs: Vec<PathBuf>,
+
+    /// The format of the input
+    #[arg(short, long, value_enum, default_value_t = Format::Json)]
+    format: Format,
+
+    /// How pieces of work are laid out in the input
+    #[arg(short, long, value_enum, default_value_t = Mode::Array)]
+    mode: Mode,
+
+    /// How results are laid out in the output
+    #[arg(short, long, value_enum, default_value_t = Output::Json)]
+    output: Output,
+
+    /// Write errors to the output in place of results and continue with the
+    /// next piece of work, instead of stopping at the first error. Continues
+    /// with the next input if the input itself is malformed. Without this,
+    /// the output ends with the results before the first error.
+    #[arg(short = 'k', long)]
+    continue_on_error: bool,
+
+    /// The number of inputs executed concurrently, each on a thread of its
+    /// own. The work within a single input is always executed in order, one
+    /// piece at a time. Defaults to the number of CPUs.
+    #[arg(short = 'j', long)]
+    jobs: Option<NonZeroUsize>,
+}
+
+#[derive(ValueEnum, Debug, Clone, Copy)]
+enum Format {
+    Json,
+    Cbor,
+    Msgpack,
+}
+
+#[derive(ValueEnum, Debug, Clone, Copy)]
+enum Mode {
+    /// A single array containing all pieces of work
+    Array,
+    /// Newline-delimited pieces of work
+    Ndjson,
+    /// Concatenated pieces of work, separated by optional whitespace
+    Concatenated,
+}
+
+#[derive(ValueEnum, Debug, Clone, Copy)]
+enum Output {
+    /// A single JSON array containing all results
+    Json,
+    /// Every result on a line of its own
+    Ndjson,
+}
+
+/// A source of work
+#[derive(Debug, Clone)]
+enum Input {
+    Stdin,
+    File(PathBuf),
+}
+
+impl Input {
+    fn open(&self) -> io::Result<Box<dyn Read + Send>> {
+        match self {
+            Input::Stdin => Ok(Box::new(io::stdin())),
+            Input::File(path) => Ok(Box::new(File::open(path)?)),
+        }
+    }
+}
+
+impl Display for Input {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Input::Stdin => write!(f, "<stdin>"),
+            Input::File(path) => write!(f, "{}", path.display()),
+        }
+    }
+}
+
+/// Reads work in either JSON or one of the [BinaryFormat]s
+enum AnyWorkReader<R> {
+    Json(WorkReader<R>),
+    Binary(BinaryWorkReader<R>),
+}
+
+impl<R: AsyncRead + Unpin> AnyWorkReader<R> {
+    fn new(reader: R, format: Format, mode: Mode) -> Self {
+        let mode = match mode {
+            Mode::Array => InputMode::Array,
+            Mode::Ndjson | Mode::Concatenated => InputMode::Values,
+        };
+        match format {
+            Format::Json => Self::Json(WorkReader::with_mode(reader, mode)),
+            Format::Cbor => Self::Binary(BinaryWorkReader::new(reader, BinaryFormat::Cbor, mode)),
+            Format::Msgpack => Self::Binary(BinaryWorkReader::new(
+                reader,
+                BinaryFormat::MessagePack,
+                mode,
+            )),
+        }
+    }
+
+    async fn next(&mut self) -> Result<Option<WorkResult>, StrompyError> {
+        match self {
+            Self::Json(reader) => reader.next().await,
+            Self::Binary(reader) => reader.next().await,
+        }
+    }
+
+    async fn next_item(&mut self) -> Result<Option<Result<WorkResult, ItemError>>, StrompyError> {
+        match self {
+            Self::Json(reader) => reader.next_item().await,
+            Self::Binary(reader) => reader.next_item().await,
+        }
+    }
+}
+
+/// The outcome of a single piece of work. The outer error
+/// is one after which the input cannot be read any further.
+type Outcome = Result<Result<WorkResult, ItemError>, StrompyError>;
+type Results = mpsc::Receiver<Outcome>;
+type ResultSender = mpsc::SyncSender<Outcome>;
+
+/// Execute all work in `input`, sending the results over `tx`. Stops at
+/// the first error after which the input cannot be read any further,
+/// or once the results are no longer received.
+fn run(input: &Input, args: &Args, tx: ResultSender) {
+    let reader = match input.open() {
+        Ok(reader) => reader,
+        Err(e) => {
+            let _ = tx.send(Err(e.into()));
+            return;
+        }
+    };
+    let reader = AllowStdIo::new(BufReader::new(reader));
+    let mut reader = AnyWorkReader::new(reader, args.format, args.mode);
+
+    block_on(async {
+        loop {
+            let res = if args.continue_on_error {
+                reader.next_item().await.transpose()
+            } else {
+                reader.next().await.transpose().map(|res| res.map(Ok))
+            };
+            let Some(res) = res else {
+                break;
+            };
+            let failed = res.is_err();
+            if tx.send(res).is_err() || failed {
+                break;
+            }
+        }
+    });
+}
+
+/// Write the results of all inputs in order. Returns whether
+/// all work succeeded, or an error if writing failed.
+async fn write_all<W: AsyncWrite + Unpin>(
+    writer: &mut WorkWriter<W>,
+    results: Vec<(Input, Results)>,
+    continue_on_error: bool,
+) -> Result<bool, StrompyError> {
+    let mut succeeded = true;
+    for (input, results) in results {
+        for res in results {
+            match res {
+                Ok(Ok(res)) => writer.write(&res).await?,
+                // Only returned when continuing on errors
+                Ok(Err(e)) => {
+                    eprintln!("strompy: {input}: Item {}: {}", e.index, e.error);
+                    succeeded = false;
+                    writer.write_item_error(&e).await?;
+                }
+                Err(e) => {
+                    eprintln!("strompy: {input}: {e}");
+                    succeeded = false;
+                    if !continue_on_error {
+                        return Ok(false);
+                    }
+                    writer.write_error(&e).await?;
+                }
+            }
+        }
+    }
+    Ok(succeeded)
+}
+
+fn main() -> ExitCode {
+    let args = Args::parse();
+
+    let inputs: Vec<Input> = match args.inputs.as_slice() {
+        [] => vec![Input::Stdin],
+        paths => paths
+            .iter()
+            .map(|path| match path.to_str() {
+                Some("-") => Input::Stdin,
+                _ => Input::File(path.clone()),
+            })
+            .collect(),
+    };
+    let threads = args
+        .jobs
+        .or_else(|| thread::available_parallelism().ok())
+        .map_or(1, NonZeroUsize::get)
+        .min(inputs.len());
+
+    // Inputs are picked up in order, so that the input of which the results
+    // are being written is always being executed. Later inputs are executed
+    // ahead, until their result buffer fills up.
+    let (queue, results): (VecDeque<_>, Vec<_>) = inputs
+        .into_iter()
+        .map(|input| {
+            let (tx, rx) = mpsc::sync_channel(RESULT_BUFFER);
+            ((input.clone(), tx), (input, rx))
+        })
+        .unzip();
+    let queue = Mutex::new(queue);
+    let next_job = || {
+        queue
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .pop_front()
+    };
+
+    let res = thread::scope(|s| {
+        for _ in 0..threads {
+            s.spawn(|| {
+                while let Some((input, tx)) = next_job() {
+                    run(&input, &args, tx);
+                }
+            });
+        }
+
+        let stdout = AllowStdIo::new(BufWriter::new(io::stdout().lock()));
+        let mode = match args.output {
+            Output::Json => OutputMode::Array,
+            Output::Ndjson => OutputMode::Ndjson,
+        };
+        let mut writer = WorkWriter::with_mode(stdout, mode);
+        // The output is finished even if work failed, so that
+        // it holds valid JSON with the results written so far
+        let res = block_on(async {
+            let succeeded = write_all(&mut writer, results, args.continue_on_error).await?;
+            writer.finish().await?;
+            Ok::<_, StrompyError>(succeeded)
+        });
+
+        // Nothing is left to write the results of any remaining inputs to
+        queue.lock().unwrap_or_else(PoisonError::into_inner).clear();
+        res
+    });
+
+    match res {
+        Ok(true) => ExitCode::SUCCESS,
+        Ok(false) => ExitCode::FAILURE,
+        Err(e) => {
+            eprintln!("strompy: Writing output failed: {e}");
+            ExitCode::FAILURE
+        }
+    }
+}
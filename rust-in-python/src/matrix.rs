@@ -0,0 +1,192 @@
+//! Python-side [MatrixBuf], which keeps the shape of a result, so that it
+//! can be inspected and passed on to new work without converting it.
+
+use core::{
+    ffi::{c_char, c_int, c_void},
+    ptr,
+};
+
+use pyo3::{
+    exceptions::{PyBufferError, PyIndexError},
+    ffi,
+    prelude::*,
+    types::{PyIterator, PyList, PyType},
+};
+
+use crate::{storage::Storage, MatrixBuf, Order, Shape};
+
+/// The size in bytes of a single element
+const ITEM_SIZE: usize = core::mem::size_of::<f64>();
+
+/// A result matrix, as returned with `matrix=True`. Rows can be indexed as
+/// `m[i]` and elements as `m[i, j]`, and iterating over the matrix results in
+/// its rows. Matrices can be passed to [WorkBuilder](crate::builder::WorkBuilder)
+/// wherever it takes a NumPy array, and are immutable.
+///
+/// Like the input, `Matrix(d, n, order="col")` creates a matrix from data `d`
+/// with `n` columns.
+#[pyclass(name = "Matrix", frozen)]
+#[derive(Debug, Clone)]
+pub struct PyMatrix {
+    m: MatrixBuf,
+    /// The shape of the exported buffer, see `__getbuffer__`
+    buffer_shape: [ffi::Py_ssize_t; 2],
+    /// The strides in bytes of the exported buffer, see `__getbuffer__`
+    buffer_strides: [ffi::Py_ssize_t; 2],
+}
+
+impl PyMatrix {
+    pub(crate) fn matrix(&self) -> &MatrixBuf {
+        &self.m
+    }
+}
+
+impl From<MatrixBuf> for PyMatrix {
+    fn from(m: MatrixBuf) -> Self {
+        let Shape { rows, cols } = m.shape();
+        Self {
+            m,
+            buffer_shape: [rows as ffi::Py_ssize_t, cols as ffi::Py_ssize_t],
+            // Elements are stored column by column
+            buffer_strides: [
+                ITEM_SIZE as ffi::Py_ssize_t,
+                (rows * ITEM_SIZE) as ffi::Py_ssize_t,
+            ],
+        }
+    }
+}
+
+/// An index into a [PyMatrix], given as either `i` or `i, j`
+#[derive(FromPyObject)]
+enum Index {
+    Element(isize, isize),
+    Row(isize),
+}
+
+/// Resolve index `i` into `len` rows or columns, as named by `axis`.
+/// Like in Python, negative indices count from the end.
+fn resolve(i: isize, len: usize, axis: &str) -> PyResult<usize> {
+    let resolved = if i < 0 { i + len as isize } else { i };
+    match usize::try_from(resolved) {
+        Ok(resolved) if resolved < len => Ok(resolved),
+        _ => Err(PyIndexError::new_err(format!(
+            "Index {i} is out of range for {len} {axis}"
+        ))),
+    }
+}
+
+#[pymethods]
+impl PyMatrix {
+    #[new]
+    #[pyo3(signature = (d, n, *, order = "col"))]
+    fn new(d: Vec<f64>, n: usize, order: &str) -> PyResult<Self> {
+        let order = Order::try_from(order.to_owned())?;
+        let m = MatrixBuf::with_order(Storage::from_slice(&d)?, n, order)?;
+        Ok(m.into())
+    }
+
+    /// The number of rows and columns, as a tuple
+    #[getter]
+    fn shape(&self) -> (usize, usize) {
+        let Shape { rows, cols } = self.m.shape();
+        (rows, cols)
+    }
+
+    fn __len__(&self) -> usize {
+        self.m.shape().rows
+    }
+
+    fn __getitem__(&self, py: Python<'_>, index: Index) -> PyResult<PyObject> {
+        let Shape { rows, cols } = self.m.shape();
+        let view = self.m.view();
+        match index {
+            Index::Element(i, j) => {
+                let (i, j) = (resolve(i, rows, "rows")?, resolve(j, cols, "columns")?);
+                Ok(view[(i, j)].into_py(py))
+            }
+            Index::Row(i) => {
+                let i = resolve(i, rows, "rows")?;
+                let row = view.row(i);
+                Ok(PyList::new_bound(py, row.iter().copied())
+                    .into_any()
+                    .unbind())
+            }
+        }
+    }
+
+    /// Iterate over the rows, each as a list
+    fn __iter__<'py>(&self, py: Python<'py>) -> PyResult<Bound<'py, PyIterator>> {
+        PyList::new_bound(py, self.m.clone()).as_any().iter()
+    }
+
+    /// Matrices are equal if they have the same shape and elements
+    fn __eq__(&self, other: PyRef<'_, Self>) -> bool {
+        self.m == other.m
+    }
+
+    fn __repr__(&self) -> String {
+        format!("Matrix({:?})", Vec::<Vec<f64>>::from(self.m.clone()))
+    }
+
+    /// Pickle the matrix as the arguments to recreate it with
+    fn __reduce__<'py>(slf: &Bound<'py, Self>) -> (Bound<'py, PyType>, (Vec<f64>, usize)) {
+        let m = &slf.get().m;
+        (slf.get_type(), (m.d.to_vec(), m.n))
+    }
+
+    /// Export the elements as a read-only buffer of doubles, so that
+    /// `numpy.asarray(m)` can use them without copying. The elements
+    /// are stored column by column, in Fortran order.
+    unsafe fn __getbuffer__(
+        slf: Bound<'_, Self>,
+        view: *mut ffi::Py_buffer,
+        flags: c_int,
+    ) -> PyResult<()> {
+        if view.is_null() {
+            return Err(PyBufferError::new_err("View is null"));
+        }
+        let flag = |f: c_int| flags & f == f;
+        if flag(ffi::PyBUF_WRITABLE) {
+            return Err(PyBufferError::new_err("Matrix is read-only"));
+        }
+
+        let this = slf.get();
+        let Shape { rows, cols } = this.m.shape();
+        // Consumers that ask for a shape without strides, or for C-contiguous
+        // data, expect the elements row by row. Only a single row or column
+        // is stored the same either way.
+        let row_by_row =
+            flag(ffi::PyBUF_C_CONTIGUOUS) || (flag(ffi::PyBUF_ND) && !flag(ffi::PyBUF_STRIDES));
+        if row_by_row && rows > 1 && cols > 1 {
+            return Err(PyBufferError::new_err(
+                "Matrix elements are stored column by column, in Fortran order",
+            ));
+        }
+
+        let view = &mut *view;
+        view.obj = slf.clone().into_any().into_ptr();
+        view.buf = this.m.d.as_ptr() as *mut c_void;
+        view.len = (this.m.d.len() * ITEM_SIZE) as ffi::Py_ssize_t;
+        view.readonly = 1;
+        view.itemsize = ITEM_SIZE as ffi::Py_ssize_t;
+        view.format = if flag(ffi::PyBUF_FORMAT) {
+            c"d".as_ptr() as *mut c_char
+        } else {
+            ptr::null_mut()
+        };
+        (view.ndim, view.shape) = if flag(ffi::PyBUF_ND) {
+            (2, this.buffer_shape.as_ptr() as *mut _)
+        } else {
+            (1, ptr::null_mut())
+        };
+        view.strides = if flag(ffi::PyBUF_STRIDES) {
+            this.buffer_strides.as_ptr() as *mut _
+        } else {
+            ptr::null_mut()
+        };
+        view.suboffsets = ptr::null_mut();
+        view.internal = ptr::null_mut();
+
+        Ok(())
+    }
+}
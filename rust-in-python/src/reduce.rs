@@ -0,0 +1,149 @@
+//! Element-wise functions and reductions, which combine the elements of
+//! a matrix into single values along its rows, its columns or as a whole.
+
+use crate::{error::StrompyError, storage::Storage, MatrixBuf, Shape, StrompyResult};
+
+/// Which elements of a matrix a reduction combines, given as `"axis"`.
+/// Defaults to all of them.
+#[derive(serde::Deserialize, Debug, Clone, Copy, Default, PartialEq)]
+#[serde(try_from = "String")]
+pub enum Axis {
+    /// All elements at once, given as `"all"`, resulting in a 1x1 matrix
+    #[default]
+    All,
+    /// The elements of each row, given as `"rows"`, resulting in a single column
+    Rows,
+    /// The elements of each column, given as `"cols"`, resulting in a single row
+    Cols,
+}
+
+impl Axis {
+    /// The shape of the result of reducing a matrix of shape `shape` along this axis
+    pub(crate) fn reduced(self, shape: Shape) -> Shape {
+        match self {
+            Axis::All => Shape { rows: 1, cols: 1 },
+            Axis::Rows => Shape {
+                rows: shape.rows,
+                cols: 1,
+            },
+            Axis::Cols => Shape {
+                rows: 1,
+                cols: shape.cols,
+            },
+        }
+    }
+}
+
+impl TryFrom<String> for Axis {
+    type Error = StrompyError;
+
+    fn try_from(value: String) -> StrompyResult<Self> {
+        match value.as_str() {
+            "all" => Ok(Axis::All),
+            "rows" => Ok(Axis::Rows),
+            "cols" => Ok(Axis::Cols),
+            _ => Err(StrompyError::InvalidValue { key: "axis", value }),
+        }
+    }
+}
+
+/// Which norm the `norm` operation computes, given as `"kind"`. Defaults to
+/// [NormKind::L2].
+#[derive(serde::Deserialize, Debug, Clone, Copy, Default, PartialEq)]
+#[serde(try_from = "String")]
+pub enum NormKind {
+    /// The sum of the absolute values, given as `"l1"`
+    L1,
+    /// The square root of the sum of the squares, given as `"l2"`.
+    /// Of a matrix as a whole, this is its Frobenius norm.
+    #[default]
+    L2,
+    /// The largest absolute value, given as `"inf"`
+    Inf,
+}
+
+impl TryFrom<String> for NormKind {
+    type Error = StrompyError;
+
+    fn try_from(value: String) -> StrompyResult<Self> {
+        match value.as_str() {
+            "l1" => Ok(NormKind::L1),
+            "l2" => Ok(NormKind::L2),
+            "inf" => Ok(NormKind::Inf),
+            _ => Err(StrompyError::InvalidValue { key: "kind", value }),
+        }
+    }
+}
+
+/// How a reduction combines elements into a single value
+#[derive(Debug, Clone, Copy)]
+pub(crate) enum Reduction {
+    Sum,
+    Mean,
+    Min,
+    Max,
+    Norm(NormKind),
+}
+
+impl Reduction {
+    fn apply(self, xs: impl Iterator<Item = f64>) -> f64 {
+        match self {
+            Reduction::Sum => xs.sum(),
+            Reduction::Mean => {
+                let (sum, count) = xs.fold((0.0, 0), |(sum, count), x| (sum + x, count + 1));
+                sum / count as f64
+            }
+            Reduction::Min => xs.fold(f64::INFINITY, f64::min),
+            Reduction::Max => xs.fold(f64::NEG_INFINITY, f64::max),
+            Reduction::Norm(NormKind::L1) => xs.map(f64::abs).sum(),
+            Reduction::Norm(NormKind::L2) => xs.map(|x| x * x).sum::<f64>().sqrt(),
+            Reduction::Norm(NormKind::Inf) => xs.map(f64::abs).fold(0.0, f64::max),
+        }
+    }
+}
+
+/// Limit `x` to lie between `min` and `max`. Like NumPy's `clip`, this
+/// results in `max` if `min` is greater than `max`, and leaves NaN as is.
+pub(crate) fn clamp(x: f64, min: Option<f64>, max: Option<f64>) -> f64 {
+    let x = match min {
+        Some(min) if x < min => min,
+        _ => x,
+    };
+    match max {
+        Some(max) if x > max => max,
+        _ => x,
+    }
+}
+
+impl MatrixBuf {
+    /// Apply `f` to every element
+    pub(crate) fn map(mut self, f: impl Fn(f64) -> f64) -> Self {
+        self.d.iter_mut().for_each(|x| *x = f(*x));
+        self
+    }
+
+    /// Combine the elements along `axis` into single values, see [Axis::reduced]
+    pub(crate) fn reduce(&self, reduction: Reduction, axis: Axis) -> StrompyResult<Self> {
+        let view = self.view();
+        let mut d = Storage::new();
+        let n = match axis {
+            Axis::All => {
+                d.push(reduction.apply(view.iter().copied()))?;
+                1
+            }
+            Axis::Rows => {
+                for row in view.row_iter() {
+                    d.push(reduction.apply(row.iter().copied()))?;
+                }
+                1
+            }
+            Axis::Cols => {
+                for column in view.column_iter() {
+                    d.push(reduction.apply(column.iter().copied()))?;
+                }
+                view.ncols()
+            }
+        };
+        Self::new(d, n)
+    }
+}
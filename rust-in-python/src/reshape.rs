@@ -0,0 +1,183 @@
+//! Slicing, reshaping and concatenating matrices, and taking diagonals.
+//! Like every [MatrixBuf], the results store their elements column by
+//! column, as [MatrixBuf::view] expects.
+
+use futures::AsyncRead;
+use struson::reader::{JsonReader, JsonStreamReader};
+
+use crate::{error::StrompyError, set_once, storage::Storage, MatrixBuf, Shape, StrompyResult};
+
+/// A range of rows or columns, given as `{"start": 0, "stop": 4, "step": 2}`.
+/// Any of these can be left out: the range starts at the first row or column,
+/// stops after the last one and selects every one of them by default.
+/// Like Python's ranges, `stop` itself is not part of the range.
+#[derive(serde::Deserialize, Debug, Clone, Copy, Default, PartialEq)]
+pub struct SliceRange {
+    pub start: Option<usize>,
+    pub stop: Option<usize>,
+    pub step: Option<usize>,
+}
+
+impl SliceRange {
+    /// The indices this range selects out of `len` rows or columns, as named
+    /// by `axis`. Fails if the range is empty or extends beyond `len`.
+    pub(crate) fn indices(
+        self,
+        axis: &'static str,
+        len: usize,
+    ) -> StrompyResult<core::iter::StepBy<core::ops::Range<usize>>> {
+        let start = self.start.unwrap_or(0);
+        let stop = self.stop.unwrap_or(len);
+        let step = match self.step.unwrap_or(1) {
+            0 => {
+                return Err(StrompyError::InvalidValue {
+                    key: "step",
+                    value: "0".to_owned(),
+                })
+            }
+            step => step,
+        };
+        if start >= stop || stop > len {
+            return Err(StrompyError::InvalidRange {
+                axis,
+                start,
+                stop,
+                len,
+            });
+        }
+        Ok((start..stop).step_by(step))
+    }
+
+    /// Read a range given as `{"start": ..., "stop": ..., "step": ...}`
+    pub(crate) async fn deserialize<R: AsyncRead + Unpin>(
+        reader: &mut JsonStreamReader<R>,
+    ) -> StrompyResult<Self> {
+        reader.begin_object().await?;
+
+        let mut range = Self::default();
+        while reader.has_next().await? {
+            match reader.next_name().await? {
+                "start" => set_once(&mut range.start, "start", reader.next_number().await??)?,
+                "stop" => set_once(&mut range.stop, "stop", reader.next_number().await??)?,
+                "step" => set_once(&mut range.step, "step", reader.next_number().await??)?,
+                _ => reader.skip_value().await?,
+            }
+        }
+
+        reader.end_object().await?;
+        Ok(range)
+    }
+}
+
+impl MatrixBuf {
+    /// Select the rows in `rows` of the columns in `cols`
+    pub(crate) fn slice(&self, rows: SliceRange, cols: SliceRange) -> StrompyResult<Self> {
+        let Shape {
+            rows: row_count,
+            cols: col_count,
+        } = self.shape();
+        let rows = rows.indices("rows", row_count)?;
+        let cols = cols.indices("cols", col_count)?;
+
+        let view = self.view();
+        let n = cols.len();
+        let mut d = Storage::new();
+        for j in cols {
+            for i in rows.clone() {
+                d.push(view[(i, j)])?;
+            }
+        }
+        Self::new(d, n)
+    }
+
+    /// Give the elements the shape `shape`, keeping them in the same order.
+    /// The number of elements has been checked to fit `shape` already.
+    pub(crate) fn reshape(self, shape: Shape) -> StrompyResult<Self> {
+        Self::new(self.d, shape.cols)
+    }
+
+    /// Append the columns of `rhs`, which has as many rows
+    pub(crate) fn hstack(mut self, rhs: &MatrixBuf) -> StrompyResult<Self> {
+        // Columns are stored one after the other, so this appends the data as is
+        for &x in rhs.d.iter() {
+            self.d.push(x)?;
+        }
+        self.n += rhs.n;
+        Ok(self)
+    }
+
+    /// Append the rows of `rhs`, which has as many columns
+    pub(crate) fn vstack(&self, rhs: &MatrixBuf) -> StrompyResult<Self> {
+        let (lhs_view, rhs_view) = (self.view(), rhs.view());
+        let mut d = Storage::new();
+        for (lhs, rhs) in lhs_view.column_iter().zip(rhs_view.column_iter()) {
+            for &x in lhs.iter().chain(rhs.iter()) {
+                d.push(x)?;
+            }
+        }
+        Self::new(d, self.n)
+    }
+
+    /// The square matrix with the elements of this row or column on
+    /// its diagonal, or the diagonal of this matrix as a column
+    pub(crate) fn diag(&self) -> StrompyResult<Self> {
+        let Shape { rows, cols } = self.shape();
+        if rows == 1 || cols == 1 {
+            let len = self.d.len();
+            let mut res = Self::zeros(len, len)?;
+            for (i, &x) in self.d.iter().enumerate() {
+                res.d[i * len + i] = x;
+            }
+            return Ok(res);
+        }
+
+        let view = self.view();
+        let mut d = Storage::new();
+        for i in 0..rows.min(cols) {
+            d.push(view[(i, i)])?;
+        }
+        Self::new(d, 1)
+    }
+}
+
+impl Shape {
+    /// The shape of the result of [MatrixBuf::diag] for a matrix of this shape
+    pub(crate) fn diag(self) -> Shape {
+        match self {
+            Shape { rows: 1, cols: len } | Shape { rows: len, cols: 1 } => Shape {
+                rows: len,
+                cols: len,
+            },
+            Shape { rows, cols } => Shape {
+                rows: rows.min(cols),
+                cols: 1,
+            },
+        }
+    }
+
+    /// Read a shape given as `[rows, cols]`
+    pub(crate) async fn deserialize<R: AsyncRead + Unpin>(
+        reader: &mut JsonStreamReader<R>,
+    ) -> StrompyResult<Self> {
+        let mut dims: Vec<usize> = Vec::new();
+        reader.begin_array().await?;
+        while reader.has_next().await? {
+            dims.push(reader.next_number().await??);
+        }
+        reader.end_array().await?;
+
+        match dims[..] {
+            [rows, cols] => Ok(Self { rows, cols }),
+            _ => Err(StrompyError::InvalidValue {
+                key: "shape",
+                value: format!("{dims:?}"),
+            }),
+        }
+    }
+}
+
+impl From<[usize; 2]> for Shape {
+    fn from([rows, cols]: [usize; 2]) -> Self {
+        Self { rows, cols }
+    }
+}
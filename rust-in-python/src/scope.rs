@@ -0,0 +1,57 @@
+//! Named results of earlier work, which later work can refer to
+
+use std::collections::HashMap;
+
+use crate::{error::StrompyError, MatrixBuf, StrompyResult};
+
+/// The results of the [PieceOfWork](crate::PieceOfWork)s in a stream
+/// that were given a name with `"as"`. Every stream has its own scope.
+///
+/// A scope can have a parent scope, which is searched for results that
+/// are not found in the scope itself. This lets work that executes in
+/// parallel store its results without touching the shared scope.
+#[derive(Debug, Default)]
+pub struct Scope<'p> {
+    parent: Option<&'p Scope<'p>>,
+    results: HashMap<String, MatrixBuf>,
+}
+
+impl Scope<'static> {
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+impl<'p> Scope<'p> {
+    /// A new, empty scope with `self` as its parent
+    pub fn child(&'p self) -> Scope<'p> {
+        Scope {
+            parent: Some(self),
+            results: HashMap::new(),
+        }
+    }
+
+    /// Get the result named `name`, failing if there is none
+    pub fn get(&self, name: &str) -> StrompyResult<&MatrixBuf> {
+        match (self.results.get(name), self.parent) {
+            (Some(res), _) => Ok(res),
+            (None, Some(parent)) => parent.get(name),
+            (None, None) => Err(StrompyError::DanglingRef(name.to_owned())),
+        }
+    }
+
+    /// Store a result under `name`, replacing any earlier result of that name
+    pub fn insert(&mut self, name: String, res: MatrixBuf) {
+        self.results.insert(name, res);
+    }
+
+    /// The results stored in this scope itself, without those of its parent
+    pub fn into_results(self) -> HashMap<String, MatrixBuf> {
+        self.results
+    }
+
+    /// Store all of `results`, replacing any earlier results of the same names
+    pub fn extend(&mut self, results: HashMap<String, MatrixBuf>) {
+        self.results.extend(results);
+    }
+}
@@ -0,0 +1,136 @@
+//! Storage for matrix data and other variable-length input.
+//!
+//! With the `heap` feature enabled, [Storage] is backed by a [Vec] and can
+//! hold any number of items. Without it, [Storage] is backed by a
+//! [heapless::Vec], which lives on the stack and holds at most `N` items.
+
+use core::{
+    fmt,
+    marker::PhantomData,
+    ops::{Deref, DerefMut},
+};
+
+use serde::de::{Deserialize, Deserializer, SeqAccess, Visitor};
+
+use crate::StrompyResult;
+
+/// A list of items, backed by either heap-allocated or stack-based storage
+#[derive(Debug, Clone, PartialEq)]
+pub struct Storage<T, const N: usize> {
+    #[cfg(feature = "heap")]
+    items: Vec<T>,
+    #[cfg(not(feature = "heap"))]
+    items: heapless::Vec<T, N>,
+}
+
+impl<T, const N: usize> Storage<T, N> {
+    pub fn new() -> Self {
+        Self {
+            items: Default::default(),
+        }
+    }
+
+    /// The maximum number of items this storage can hold,
+    /// or `None` if it is unbounded
+    pub const fn capacity() -> Option<usize> {
+        if cfg!(feature = "heap") {
+            None
+        } else {
+            Some(N)
+        }
+    }
+
+    /// Append an item, failing if the storage is full
+    pub fn push(&mut self, item: T) -> StrompyResult<()> {
+        #[cfg(feature = "heap")]
+        self.items.push(item);
+        #[cfg(not(feature = "heap"))]
+        self.items
+            .push(item)
+            .map_err(|_| crate::StrompyError::Capacity { capacity: N })?;
+        Ok(())
+    }
+
+    /// Resize the storage to hold `len` items, filling any new slots with `value`
+    pub fn resize(&mut self, len: usize, value: T) -> StrompyResult<()>
+    where
+        T: Clone,
+    {
+        #[cfg(feature = "heap")]
+        self.items.resize(len, value);
+        #[cfg(not(feature = "heap"))]
+        self.items
+            .resize(len, value)
+            .map_err(|_| crate::StrompyError::Capacity { capacity: N })?;
+        Ok(())
+    }
+
+    /// Create a new [Storage] holding a copy of `items`
+    pub fn from_slice(items: &[T]) -> StrompyResult<Self>
+    where
+        T: Clone,
+    {
+        let mut storage = Self::new();
+        for item in items {
+            storage.push(item.clone())?;
+        }
+        Ok(storage)
+    }
+}
+
+impl<T, const N: usize> Default for Storage<T, N> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<T, const N: usize> Deref for Storage<T, N> {
+    type Target = [T];
+
+    fn deref(&self) -> &Self::Target {
+        &self.items
+    }
+}
+
+impl<T, const N: usize> DerefMut for Storage<T, N> {
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        &mut self.items
+    }
+}
+
+impl<T, const N: usize> IntoIterator for Storage<T, N> {
+    type Item = T;
+
+    #[cfg(feature = "heap")]
+    type IntoIter = <Vec<T> as IntoIterator>::IntoIter;
+    #[cfg(not(feature = "heap"))]
+    type IntoIter = <heapless::Vec<T, N> as IntoIterator>::IntoIter;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.items.into_iter()
+    }
+}
+
+impl<'de, T: Deserialize<'de>, const N: usize> Deserialize<'de> for Storage<T, N> {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        struct StorageVisitor<T, const N: usize>(PhantomData<T>);
+
+        impl<'de, T: Deserialize<'de>, const N: usize> Visitor<'de> for StorageVisitor<T, N> {
+            type Value = Storage<T, N>;
+
+            fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+                f.write_str("a sequence")
+            }
+
+            fn visit_seq<A: SeqAccess<'de>>(self, mut seq: A) -> Result<Self::Value, A::Error> {
+                let mut storage = Storage::new();
+                while let Some(item) = seq.next_element()? {
+                    storage.push(item).map_err(serde::de::Error::custom)?;
+                }
+                Ok(storage)
+            }
+        }
+
+        deserializer.deserialize_seq(StorageVisitor(PhantomData))
+    }
+}
@@ -0,0 +1,613 @@
+//! Checking work without executing it. Validation covers everything execution
+//! would check, like keys, operation codes, capacities and the shapes of all
+//! operands, but does not do any arithmetic. Instead of stopping at the first
+//! problem, it reports every problem it finds.
+
+use std::{collections::HashMap, fmt};
+
+use futures::AsyncRead;
+use serde::de::{
+    Deserialize, DeserializeOwned, Deserializer, Error as _, MapAccess, SeqAccess, Unexpected,
+    Visitor,
+};
+use serde_json::{Map, Value};
+use struson::reader::{JsonReader, JsonStreamReader, ValueType};
+
+use crate::{
+    error::{Location, PathPiece, StrompyError},
+    storage::Storage,
+    BinaryFormat, MatrixBuf, OpChain, OpParams, Operand, Operation, Order, Shape, StrompyResult,
+    MATRIX_CAPACITY, MAX_DEPTH,
+};
+
+/// The keys work is made up of, apart from those in [OpParams::KEYS]
+const KEYS: [&str; 10] = [
+    "lhs", "op", "expr", "as", "ref", "d", "n", "order", "code", "rhs",
+];
+
+/// A value read from the input, along with every key of the input format that
+/// occurs more than once in one of its objects. Of such a key, only the last
+/// value is kept. Other keys are skipped when work is read, so they may repeat.
+pub struct ReadValue {
+    value: Value,
+    /// Every duplicate key, along with its path relative to `value`
+    duplicates: Vec<(Vec<PathPiece>, &'static str)>,
+}
+
+impl ReadValue {
+    /// A value that holds no objects with duplicate keys
+    fn new(value: Value) -> Self {
+        Self {
+            value,
+            duplicates: Vec::new(),
+        }
+    }
+
+    /// Build an array from its items
+    fn array(items: impl IntoIterator<Item = ReadValue>) -> Self {
+        let mut duplicates = Vec::new();
+        let mut array = Vec::new();
+        for item in items {
+            duplicates.extend(item.duplicates_within(PathPiece::Index(array.len())));
+            array.push(item.value);
+        }
+        Self {
+            value: Value::Array(array),
+            duplicates,
+        }
+    }
+
+    /// Build an object from its members, in the order in which they occur
+    fn object(members: impl IntoIterator<Item = (String, ReadValue)>) -> Self {
+        let mut duplicates = Vec::new();
+        let mut object = Map::new();
+        for (name, member) in members {
+            let piece = PathPiece::Key(name.clone());
+            let key = KEYS.iter().chain(&OpParams::KEYS).find(|&&key| key == name);
+            if let (Some(key), true) = (key, object.contains_key(&name)) {
+                duplicates.push((vec![piece.clone()], *key));
+            }
+            duplicates.extend(member.duplicates_within(piece));
+            object.insert(name, member.value);
+        }
+        Self {
+            value: Value::Object(object),
+            duplicates,
+        }
+    }
+
+    /// The duplicate keys of this value, relative to the value that
+    /// holds it at `piece`
+    fn duplicates_within(
+        &self,
+        piece: PathPiece,
+    ) -> impl Iterator<Item = (Vec<PathPiece>, &'static str)> + '_ {
+        self.duplicates.iter().map(move |(path, key)| {
+            let mut path = path.clone();
+            path.insert(0, piece.clone());
+            (path, *key)
+        })
+    }
+}
+
+impl<'de> Deserialize<'de> for ReadValue {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        struct ReadValueVisitor;
+
+        impl<'de> Visitor<'de> for ReadValueVisitor {
+            type Value = ReadValue;
+
+            fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+                f.write_str("any value")
+            }
+
+            fn visit_bool<E>(self, b: bool) -> Result<Self::Value, E> {
+                Ok(ReadValue::new(Value::Bool(b)))
+            }
+
+            fn visit_i64<E>(self, n: i64) -> Result<Self::Value, E> {
+                Ok(ReadValue::new(Value::from(n)))
+            }
+
+            fn visit_u64<E>(self, n: u64) -> Result<Self::Value, E> {
+                Ok(ReadValue::new(Value::from(n)))
+            }
+
+            fn visit_f64<E>(self, n: f64) -> Result<Self::Value, E> {
+                Ok(ReadValue::new(Value::from(n)))
+            }
+
+            fn visit_str<E>(self, s: &str) -> Result<Self::Value, E> {
+                Ok(ReadValue::new(Value::from(s)))
+            }
+
+            fn visit_string<E>(self, s: String) -> Result<Self::Value, E> {
+                Ok(ReadValue::new(Value::String(s)))
+            }
+
+            fn visit_none<E>(self) -> Result<Self::Value, E> {
+                Ok(ReadValue::new(Value::Null))
+            }
+
+            fn visit_some<D: Deserializer<'de>>(self, d: D) -> Result<Self::Value, D::Error> {
+                ReadValue::deserialize(d)
+            }
+
+            fn visit_unit<E>(self) -> Result<Self::Value, E> {
+                Ok(ReadValue::new(Value::Null))
+            }
+
+            fn visit_seq<A: SeqAccess<'de>>(self, mut seq: A) -> Result<Self::Value, A::Error> {
+                let mut items = Vec::new();
+                while let Some(item) = seq.next_element()? {
+                    items.push(item);
+                }
+                Ok(ReadValue::array(items))
+            }
+
+            fn visit_map<A: MapAccess<'de>>(self, mut map: A) -> Result<Self::Value, A::Error> {
+                let mut members = Vec::new();
+                while let Some(member) = map.next_entry()? {
+                    members.push(member);
+                }
+                Ok(ReadValue::object(members))
+            }
+        }
+
+        deserializer.deserialize_any(ReadValueVisitor)
+    }
+}
+
+/// Checks [PieceOfWork](crate::PieceOfWork)s given as JSON values,
+/// collecting any problems found
+#[derive(Debug, Default)]
+pub struct Validator {
+    /// The shapes of the results named so far, which
+    /// are `None` if the named work itself has problems
+    shapes: HashMap<String, Option<Shape>>,
+    /// The problems found so far, each located by its JSON path
+    problems: Vec<StrompyError>,
+}
+
+/// What is known about the result of some work
+enum Outcome {
+    /// A single matrix, of which the shape is known if there were no problems
+    Matrix(Option<Shape>),
+    /// The factors of the decomposition `op`, each with
+    /// its shape if there were no problems
+    Factors {
+        op: &'static str,
+        factors: Vec<(&'static str, Option<Shape>)>,
+    },
+}
+
+/// The result of checking the type of a single field. Problems are
+/// collected by the [Validator], so they do not carry any information.
+type Checked<T> = Result<T, ()>;
+
+impl Validator {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// The problems found so far
+    pub fn into_problems(self) -> Vec<StrompyError> {
+        self.problems
+    }
+
+    /// Record a problem that is not tied to a single piece of work, like malformed input
+    pub fn push(&mut self, problem: StrompyError) {
+        self.problems.push(problem);
+    }
+
+    /// Check a single piece of work at `path` in the input
+    pub fn piece_of_work(&mut self, item: &ReadValue, path: &[PathPiece]) {
+        for (duplicate, key) in &item.duplicates {
+            let path = [path, &duplicate[..]].concat();
+            self.problem::<()>(&path, StrompyError::DuplicateKey(key));
+        }
+
+        let value = &item.value;
+        let Some(object) = self.object(value, path) else {
+            return;
+        };
+        let name = self.field::<String>(object, "as", path);
+
+        let outcome = match (object.get("expr"), object.get("lhs"), object.get("op")) {
+            (Some(_), Some(_), _) => {
+                Outcome::Matrix(self.problem(path, StrompyError::ConflictingKeys("expr", "lhs")))
+            }
+            (Some(_), None, Some(_)) => {
+                Outcome::Matrix(self.problem(path, StrompyError::ConflictingKeys("expr", "op")))
+            }
+            (Some(expr), None, None) => Outcome::Matrix(self.operand(expr, &key(path, "expr"))),
+            (None, lhs, op) => {
+                let lhs = match lhs {
+                    Some(lhs) => self.operand(lhs, &key(path, "lhs")),
+                    None => self.problem(path, StrompyError::MissingKey("lhs")),
+                };
+                match op {
+                    Some(op) => self.op_chain(op, &key(path, "op"), lhs),
+                    None => Outcome::Matrix(self.problem(path, StrompyError::MissingKey("op"))),
+                }
+            }
+        };
+
+        let Ok(Some(name)) = name else {
+            return;
+        };
+        match outcome {
+            Outcome::Matrix(shape) => {
+                self.shapes.insert(name, shape);
+            }
+            Outcome::Factors { factors, .. } => {
+                for (part, shape) in factors {
+                    self.shapes.insert(format!("{name}.{part}"), shape);
+                }
+            }
+        }
+    }
+
+    /// Check a chain of operations applied to a matrix of shape `lhs`
+    fn op_chain(&mut self, value: &Value, path: &[PathPiece], lhs: Option<Shape>) -> Outcome {
+        let Value::Array(ops) = value else {
+            return Outcome::Matrix(self.unexpected(value, "an array", path));
+        };
+        if let Some(capacity) = OpChain::capacity().filter(|&capacity| ops.len() > capacity) {
+            self.problem::<()>(path, StrompyError::Capacity { capacity });
+        }
+
+        // Any problem in an operation makes the shapes of later results
+        // unknown, but the operations themselves are still checked
+        ops.iter()
+            .enumerate()
+            .fold(Outcome::Matrix(lhs), |outcome, (i, op)| {
+                let path = index(path, i);
+                // Only the last operation can be a decomposition
+                let lhs = match outcome {
+                    Outcome::Matrix(shape) => shape,
+                    Outcome::Factors { op: code, .. } => {
+                        self.problem(&path, StrompyError::NotAMatrix { op: code })
+                    }
+                };
+                match self.object(op, &path) {
+                    Some(object) => self.operation(object, &path, lhs),
+                    None => Outcome::Matrix(None),
+                }
+            })
+    }
+
+    /// Check an operation, given as the fields of `object`, applied to
+    /// a matrix of shape `lhs`
+    fn operation(
+        &mut self,
+        object: &Map<String, Value>,
+        path: &[PathPiece],
+        lhs: Option<Shape>,
+    ) -> Outcome {
+        let code = self.field::<String>(object, "code", path);
+        let rhs = object
+            .get("rhs")
+            .map(|rhs| self.operand(rhs, &key(path, "rhs")));
+        let params = self.params(object, path);
+        let (Ok(code), Ok(params)) = (code, params) else {
+            return Outcome::Matrix(None);
+        };
+        let Some(code) = code else {
+            return Outcome::Matrix(self.problem(path, StrompyError::MissingKey("code")));
+        };
+
+        // Only the shape of the right-hand side matters, which is passed to
+        // the check separately, so a placeholder stands in for the operand itself
+        let placeholder = rhs.map(|_| Operand::Ref(String::new()));
+        let op = match Operation::from_fields(&code, placeholder, params) {
+            Ok(op) => op,
+            Err(e) => return Outcome::Matrix(self.problem(path, e)),
+        };
+
+        // The operation can only be checked if the shapes of both sides are known
+        let shape = match (lhs, rhs) {
+            (None, _) | (_, Some(None)) => None,
+            (Some(lhs), rhs) => {
+                let rhs = rhs.flatten();
+                match op.check(lhs, |_| rhs.ok_or(StrompyError::MissingKey("rhs"))) {
+                    Ok(shape) => Some(shape),
+                    Err(e) => self.problem(path, e),
+                }
+            }
+        };
+
+        let Some(names) = op.factor_names() else {
+            return Outcome::Matrix(shape.and_then(|shape| self.fits(&op, shape, path)));
+        };
+        // Execution fails at the first factor that does not fit, so only that one is reported
+        let shapes = shape.and_then(|lhs| op.factor_shapes(lhs));
+        let fit = shapes.as_ref().is_some_and(|shapes| {
+            shapes
+                .iter()
+                .all(|&(_, shape)| self.fits(&op, shape, path).is_some())
+        });
+        let factors = match shapes.filter(|_| fit) {
+            Some(shapes) => shapes
+                .into_iter()
+                .map(|(name, shape)| (name, Some(shape)))
+                .collect(),
+            None => names.iter().map(|&name| (name, None)).collect(),
+        };
+        Outcome::Factors {
+            op: op.code(),
+            factors,
+        }
+    }
+
+    /// Check that a matrix of shape `shape`, resulting from `op`,
+    /// fits in the storage capacity, returning the shape if it does
+    fn fits(&mut self, op: &Operation, shape: Shape, path: &[PathPiece]) -> Option<Shape> {
+        let capacity = Storage::<f64, MATRIX_CAPACITY>::capacity();
+        match (shape.size(), capacity) {
+            (None, _) => self.problem(path, StrompyError::ShapeOverflow { op: op.code() }),
+            (Some(size), Some(capacity)) if size > capacity => {
+                self.problem(path, StrompyError::Capacity { capacity })
+            }
+            _ => Some(shape),
+        }
+    }
+
+    /// Check the parameters of an operation, given as the fields of `object`
+    fn params(&mut self, object: &Map<String, Value>, path: &[PathPiece]) -> Checked<OpParams> {
+        let factor = self.field(object, "factor", path);
+        let exponent = self.field(object, "exponent", path);
+        let min = self.field(object, "min", path);
+        let max = self.field(object, "max", path);
+        let axis = self.field(object, "axis", path);
+        let kind = self.field(object, "kind", path);
+        let rows = self.field(object, "rows", path);
+        let cols = self.field(object, "cols", path);
+        let shape = self.field(object, "shape", path);
+        Ok(OpParams {
+            factor: factor?,
+            exponent: exponent?,
+            min: min?,
+            max: max?,
+            axis: axis?,
+            kind: kind?,
+            rows: rows?,
+            cols: cols?,
+            shape: shape?,
+        })
+    }
+
+    /// Check an operand, returning its shape if it is known
+    fn operand(&mut self, value: &Value, path: &[PathPiece]) -> Option<Shape> {
+        let object = self.object(value, path)?;
+        let alias = self.field::<String>(object, "as", path);
+
+        let has = |key: &str| object.contains_key(key);
+        let conflict = |key: &'static str, others: &[&'static str]| {
+            let other = others.iter().copied().find(|&other| has(other))?;
+            Some(StrompyError::ConflictingKeys(key, other))
+        };
+        let operands = ["d", "n", "order", "lhs", "rhs"];
+        let has_params = OpParams::KEYS.iter().any(|key| has(key));
+        let conflict = match (has("ref"), has("code")) {
+            (true, true) => Some(StrompyError::ConflictingKeys("ref", "code")),
+            (true, false) => conflict("ref", &[&operands[..], &OpParams::KEYS[..]].concat()),
+            (false, true) => conflict("code", &["d", "n", "order"]),
+            (false, false) if has("lhs") || has("rhs") || has_params => {
+                Some(StrompyError::MissingKey("code"))
+            }
+            (false, false) => None,
+        };
+
+        let shape = match conflict {
+            Some(e) => self.problem(path, e),
+            None if has("ref") => self.reference(object, path),
+            None if has("code") => {
+                let lhs = match object.get("lhs") {
+                    Some(lhs) => self.operand(lhs, &key(path, "lhs")),
+                    None => self.problem(path, StrompyError::MissingKey("lhs")),
+                };
+                match self.operation(object, path, lhs) {
+                    Outcome::Matrix(shape) => shape,
+                    Outcome::Factors { op, .. } => {
+                        self.problem(path, StrompyError::NotAMatrix { op })
+                    }
+                }
+            }
+            None => self.matrix(object, path),
+        };
+
+        if let Ok(Some(alias)) = alias {
+            self.shapes.insert(alias, shape);
+        }
+        shape
+    }
+
+    /// Look up the shape of the result a `{"ref": "name"}` operand refers to
+    fn reference(&mut self, object: &Map<String, Value>, path: &[PathPiece]) -> Option<Shape> {
+        let name = self.field::<String>(object, "ref", path).ok()??;
+        match self.shapes.get(&name) {
+            Some(&shape) => shape,
+            None => self.problem(path, StrompyError::DanglingRef(name)),
+        }
+    }
+
+    /// Check a `{"d": [...], "n": n}` operand, returning its shape.
+    /// The `order` of the data does not affect the shape.
+    fn matrix(&mut self, object: &Map<String, Value>, path: &[PathPiece]) -> Option<Shape> {
+        let d = self.field::<Vec<f64>>(object, "d", path);
+        let n = self.field::<usize>(object, "n", path);
+        let order = self.field::<Order>(object, "order", path);
+        let (Ok(d), Ok(n), Ok(_)) = (d, n, order) else {
+            return None;
+        };
+
+        let (Some(d), Some(n)) = (d, n) else {
+            let missing = if d.is_none() { "d" } else { "n" };
+            return self.problem(path, StrompyError::MissingKey(missing));
+        };
+        let d = match Storage::from_slice(&d) {
+            Ok(d) => d,
+            Err(e) => return self.problem(&key(path, "d"), e),
+        };
+        match MatrixBuf::new(d, n) {
+            Ok(m) => Some(m.shape()),
+            Err(e) => self.problem(path, e),
+        }
+    }
+
+    /// Get `value` as an object, recording a problem if it is not one
+    fn object<'v>(
+        &mut self,
+        value: &'v Value,
+        path: &[PathPiece],
+    ) -> Option<&'v Map<String, Value>> {
+        match value {
+            Value::Object(object) => Some(object),
+            value => self.unexpected(value, "an object", path),
+        }
+    }
+
+    /// Get the field `key` of `object`, if it is present. If it is present
+    /// but not of the expected type, a problem is recorded instead.
+    fn field<T: DeserializeOwned>(
+        &mut self,
+        object: &Map<String, Value>,
+        key: &'static str,
+        path: &[PathPiece],
+    ) -> Checked<Option<T>> {
+        let Some(value) = object.get(key) else {
+            return Ok(None);
+        };
+
+        match serde_path_to_error::deserialize(value) {
+            Ok(value) => Ok(Some(value)),
+            Err(e) => {
+                let mut location = Location::of_path(e.path());
+                location.path.splice(0..0, self::key(path, key));
+                self.problems
+                    .push(StrompyError::Serde(e.into_inner()).at(location));
+                Err(())
+            }
+        }
+    }
+
+    /// Record that `value` is not of the `expected` type
+    fn unexpected<T>(&mut self, value: &Value, expected: &str, path: &[PathPiece]) -> Option<T> {
+        let unexpected = match value {
+            Value::Null => Unexpected::Unit,
+            Value::Bool(b) => Unexpected::Bool(*b),
+            Value::Number(_) => Unexpected::Other("number"),
+            Value::String(s) => Unexpected::Str(s),
+            Value::Array(_) => Unexpected::Seq,
+            Value::Object(_) => Unexpected::Map,
+        };
+        let e = serde_json::Error::invalid_type(unexpected, &expected);
+        self.problem(path, StrompyError::Serde(e))
+    }
+
+    /// Record a problem at `path`. Returns `None`, as
+    /// whatever has the problem has no known shape.
+    fn problem<T>(&mut self, path: &[PathPiece], e: StrompyError) -> Option<T> {
+        let location = Location {
+            path: path.to_vec(),
+            ..Default::default()
+        };
+        self.problems.push(e.at(location));
+        None
+    }
+}
+
+/// `path`, extended with the member `key`
+fn key(path: &[PathPiece], key: &str) -> Vec<PathPiece> {
+    let mut path = path.to_vec();
+    path.push(PathPiece::Key(key.to_owned()));
+    path
+}
+
+/// `path`, extended with the array item at `i`
+fn index(path: &[PathPiece], i: usize) -> Vec<PathPiece> {
+    let mut path = path.to_vec();
+    path.push(PathPiece::Index(i));
+    path
+}
+
+/// Read the next value from `reader` in full, which is nested `depth` levels
+/// deep in the input. Fails if it is nested more than [MAX_DEPTH] levels deep.
+pub(crate) async fn read_value<R: AsyncRead + Unpin>(
+    reader: &mut JsonStreamReader<R>,
+    depth: usize,
+) -> StrompyResult<ReadValue> {
+    let value_type = reader.peek().await?;
+    if matches!(value_type, ValueType::Object | ValueType::Array) && depth >= MAX_DEPTH {
+        let max_depth = MAX_DEPTH;
+        return Err(StrompyError::TooDeep { max_depth });
+    }
+
+    let value = match value_type {
+        ValueType::Object => {
+            let mut members = Vec::new();
+            reader.begin_object().await?;
+            while reader.has_next().await? {
+                let name = reader.next_name().await?.to_owned();
+                members.push((name, Box::pin(read_value(reader, depth + 1)).await?));
+            }
+            reader.end_object().await?;
+            return Ok(ReadValue::object(members));
+        }
+        ValueType::Array => {
+            let mut items = Vec::new();
+            reader.begin_array().await?;
+            while reader.has_next().await? {
+                items.push(Box::pin(read_value(reader, depth + 1)).await?);
+            }
+            reader.end_array().await?;
+            return Ok(ReadValue::array(items));
+        }
+        ValueType::String => Value::String(reader.next_str().await?.to_owned()),
+        ValueType::Number => {
+            let number = reader.next_number_as_str().await?;
+            Value::Number(number.parse().map_err(StrompyError::Serde)?)
+        }
+        ValueType::Boolean => Value::Bool(reader.next_bool().await?),
+        ValueType::Null => {
+            reader.next_null().await?;
+            Value::Null
+        }
+    };
+
+    Ok(ReadValue::new(value))
+}
+
+/// Check a JSON array of [PieceOfWork](crate::PieceOfWork)s that has already
+/// been read fully into memory, without executing it. Returns every problem found.
+pub fn validate(json_bytes: &[u8]) -> Vec<StrompyError> {
+    let mut deserializer = serde_json::Deserializer::from_slice(json_bytes);
+    let work: Vec<ReadValue> = match serde_path_to_error::deserialize(&mut deserializer) {
+        Ok(work) => work,
+        Err(e) => {
+            let location = Location::of_serde(&e, json_bytes);
+            return vec![StrompyError::from(e.into_inner()).at(location)];
+        }
+    };
+
+    validate_all(&work)
+}
+
+/// Check an array of [PieceOfWork](crate::PieceOfWork)s encoded in `format`
+/// that has already been read fully into memory, without executing it.
+/// Returns every problem found.
+pub fn validate_binary(bytes: &[u8], format: BinaryFormat) -> Vec<StrompyError> {
+    match format.decode::<Vec<ReadValue>>(bytes, 0) {
+        Ok(work) => validate_all(&work),
+        Err(e) => vec![e],
+    }
+}
+
+fn validate_all(work: &[ReadValue]) -> Vec<StrompyError> {
+    let mut validator = Validator::new();
+    for (i, piece) in work.iter().enumerate() {
+        validator.piece_of_work(piece, &[PathPiece::Index(i)]);
+    }
+    validator.into_problems()
+}
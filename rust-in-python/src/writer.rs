@@ -0,0 +1,330 @@
+//! Streaming output of results as JSON.
+
+#[cfg(feature = "python")]
+use core::{
+    pin::Pin,
+    task::{ready, Context, Poll},
+};
+
+#[cfg(feature = "python")]
+use futures::channel::mpsc;
+use futures::{AsyncWrite, AsyncWriteExt};
+use struson::writer::{JsonStreamWriter, JsonWriter};
+
+use crate::{ItemError, MatrixBuf, StrompyError, StrompyResult, WorkResult};
+
+impl MatrixBuf {
+    /// Write the matrix as a JSON object, in the same format
+    /// [MatrixBuf::deserialize] reads. The data is written column by column,
+    /// which the object states explicitly:
+    ///
+    /// ```json
+    /// { "d": [...], "n": 2, "order": "col" }
+    /// ```
+    ///
+    /// JSON has no numbers for NaN and infinity, which some operations result
+    /// in, so elements that are not finite are written as `null` instead.
+    pub async fn serialize<W: AsyncWrite + Unpin>(
+        &self,
+        writer: &mut JsonStreamWriter<W>,
+    ) -> StrompyResult<()> {
+        writer.begin_object().await?;
+
+        writer.name("d").await?;
+        writer.begin_array().await?;
+        for &x in self.d.iter() {
+            if x.is_finite() {
+                writer.fp_number_value(x).await?;
+            } else {
+                writer.null_value().await?;
+            }
+        }
+        writer.end_array().await?;
+
+        writer.name("n").await?;
+        writer.number_value(self.n).await?;
+
+        writer.name("order").await?;
+        writer.string_value("col").await?;
+
+        writer.end_object().await?;
+
+        Ok(())
+    }
+}
+
+impl WorkResult {
+    /// Write the result as a JSON object. A single matrix is written as
+    /// [MatrixBuf::serialize] writes it, and the factors of a decomposition
+    /// are written as an object holding every factor under its name:
+    ///
+    /// ```json
+    /// { "q": { "d": [...], "n": 2, "order": "col" }, "r": { "d": [...], "n": 2, "order": "col" } }
+    /// ```
+    pub async fn serialize<W: AsyncWrite + Unpin>(
+        &self,
+        writer: &mut JsonStreamWriter<W>,
+    ) -> StrompyResult<()> {
+        match self {
+            WorkResult::Matrix(m) => m.serialize(writer).await,
+            WorkResult::Factors(factors) => {
+                writer.begin_object().await?;
+                for (name, m) in factors.iter() {
+                    writer.name(name).await?;
+                    m.serialize(writer).await?;
+                }
+                writer.end_object().await?;
+                Ok(())
+            }
+        }
+    }
+}
+
+impl StrompyError {
+    /// Write the error as a JSON object, holding the error message
+    /// and the location at which it occurred, if known:
+    ///
+    /// ```json
+    /// { "error": "...", "path": "$[0].op[1]", "offset": 42, "line": 3, "column": 7 }
+    /// ```
+    pub async fn serialize<W: AsyncWrite + Unpin>(
+        &self,
+        writer: &mut JsonStreamWriter<W>,
+    ) -> StrompyResult<()> {
+        writer.begin_object().await?;
+        self.serialize_members(writer).await?;
+        writer.end_object().await?;
+
+        Ok(())
+    }
+
+    /// Write the members of the object [StrompyError::serialize] writes
+    async fn serialize_members<W: AsyncWrite + Unpin>(
+        &self,
+        writer: &mut JsonStreamWriter<W>,
+    ) -> StrompyResult<()> {
+        writer.name("error").await?;
+        writer.string_value(&self.kind().to_string()).await?;
+
+        if let Some(location) = self.location() {
+            writer.name("path").await?;
+            writer.string_value(&location.json_path()).await?;
+            let position = [
+                ("offset", location.offset),
+                ("line", location.line),
+                ("column", location.column),
+            ];
+            for (name, value) in position {
+                if let Some(value) = value {
+                    writer.name(name).await?;
+                    writer.number_value(value).await?;
+                }
+            }
+        }
+
+        Ok(())
+    }
+}
+
+impl ItemError {
+    /// Write the error as a JSON object, like [StrompyError::serialize]
+    /// does, with the index of the item added:
+    ///
+    /// ```json
+    /// { "index": 0, "error": "...", "path": "$[0].op[1]", "offset": 42, "line": 3, "column": 7 }
+    /// ```
+    pub async fn serialize<W: AsyncWrite + Unpin>(
+        &self,
+        writer: &mut JsonStreamWriter<W>,
+    ) -> StrompyResult<()> {
+        writer.begin_object().await?;
+        writer.name("index").await?;
+        writer.number_value(self.index).await?;
+        self.error.serialize_members(writer).await?;
+        writer.end_object().await?;
+
+        Ok(())
+    }
+}
+
+/// How a [WorkWriter] lays out the results it writes
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum OutputMode {
+    /// A single JSON array containing all results
+    #[default]
+    Array,
+    /// Every result on a line of its own, as newline-delimited JSON
+    Ndjson,
+}
+
+impl std::str::FromStr for OutputMode {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "array" => Ok(Self::Array),
+            "ndjson" => Ok(Self::Ndjson),
+            _ => Err(format!(
+                r#"Unknown output mode "{s}", expected "array" or "ndjson""#
+            )),
+        }
+    }
+}
+
+enum Output<W> {
+    Array {
+        writer: JsonStreamWriter<W>,
+        in_array: bool,
+    },
+    Ndjson(W),
+}
+
+/// A single item of output
+enum Item<'a> {
+    Result(&'a WorkResult),
+    Error(&'a StrompyError),
+    ItemError(&'a ItemError),
+}
+
+impl Item<'_> {
+    async fn serialize<W: AsyncWrite + Unpin>(
+        &self,
+        writer: &mut JsonStreamWriter<W>,
+    ) -> StrompyResult<()> {
+        match self {
+            Item::Result(res) => res.serialize(writer).await,
+            Item::Error(e) => e.serialize(writer).await,
+            Item::ItemError(e) => e.serialize(writer).await,
+        }
+    }
+}
+
+/// Writes [WorkResult]s one by one, laid out according to an [OutputMode]
+pub struct WorkWriter<W> {
+    output: Output<W>,
+    /// Whether writing an item failed part way, after which
+    /// the array it is in cannot be closed anymore
+    broken: bool,
+}
+
+impl<W: AsyncWrite + Unpin> WorkWriter<W> {
+    /// Create a [WorkWriter] that writes a single JSON array
+    pub fn new(writer: W) -> Self {
+        Self::with_mode(writer, OutputMode::Array)
+    }
+
+    pub fn with_mode(writer: W, mode: OutputMode) -> Self {
+        let output = match mode {
+            OutputMode::Array => Output::Array {
+                writer: JsonStreamWriter::new(writer),
+                in_array: false,
+            },
+            OutputMode::Ndjson => Output::Ndjson(writer),
+        };
+        Self {
+            output,
+            broken: false,
+        }
+    }
+
+    /// Write a single result as the next item of the output
+    pub async fn write(&mut self, res: &WorkResult) -> StrompyResult<()> {
+        self.write_item(Item::Result(res)).await
+    }
+
+    /// Write an error as the next item of the output, in place of a result.
+    /// See [StrompyError::serialize] for the format.
+    pub async fn write_error(&mut self, e: &StrompyError) -> StrompyResult<()> {
+        self.write_item(Item::Error(e)).await
+    }
+
+    /// Write the error in a single piece of work as the next item of the
+    /// output, in place of its result. See [ItemError::serialize] for the format.
+    pub async fn write_item_error(&mut self, e: &ItemError) -> StrompyResult<()> {
+        self.write_item(Item::ItemError(e)).await
+    }
+
+    async fn write_item(&mut self, item: Item<'_>) -> StrompyResult<()> {
+        match &mut self.output {
+            Output::Array { writer, in_array } => {
+                Self::begin(writer, in_array).await?;
+                let res = item.serialize(writer).await;
+                self.broken |= res.is_err();
+                res
+            }
+            Output::Ndjson(writer) => {
+                let mut line = JsonStreamWriter::new(&mut *writer);
+                item.serialize(&mut line).await?;
+                line.finish_document().await?;
+                writer.write_all(b"\n").await?;
+                Ok(())
+            }
+        }
+    }
+
+    /// Open the array, unless it is open already
+    async fn begin(writer: &mut JsonStreamWriter<W>, in_array: &mut bool) -> StrompyResult<()> {
+        if !*in_array {
+            writer.begin_array().await?;
+            *in_array = true;
+        }
+        Ok(())
+    }
+
+    /// Close the array, if any, and flush any buffered output. If writing
+    /// an item failed part way, the output is left as it is.
+    pub async fn finish(self) -> StrompyResult<()> {
+        match self.output {
+            Output::Array { .. } if self.broken => {}
+            Output::Array {
+                mut writer,
+                mut in_array,
+            } => {
+                Self::begin(&mut writer, &mut in_array).await?;
+                writer.end_array().await?;
+                writer.finish_document().await?;
+            }
+            Output::Ndjson(mut writer) => writer.flush().await?,
+        }
+        Ok(())
+    }
+}
+
+/// An [AsyncWrite] that sends all bytes written to it over a channel.
+/// Dropping or closing the writer ends the channel.
+#[cfg(feature = "python")]
+pub struct ChannelWriter {
+    tx: mpsc::Sender<Vec<u8>>,
+}
+
+#[cfg(feature = "python")]
+impl ChannelWriter {
+    pub fn new(tx: mpsc::Sender<Vec<u8>>) -> Self {
+        Self { tx }
+    }
+}
+
+#[cfg(feature = "python")]
+impl AsyncWrite for ChannelWriter {
+    fn poll_write(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &[u8],
+    ) -> Poll<std::io::Result<usize>> {
+        let tx = &mut self.get_mut().tx;
+        ready!(tx.poll_ready(cx)).map_err(|_| std::io::ErrorKind::BrokenPipe)?;
+        tx.start_send(buf.to_vec())
+            .map_err(|_| std::io::ErrorKind::BrokenPipe)?;
+        Poll::Ready(Ok(buf.len()))
+    }
+
+    fn poll_flush(self: Pin<&mut Self>, _cx: &mut Context<'_>) -> Poll<std::io::Result<()>> {
+        // Every write is sent over the channel right away
+        Poll::Ready(Ok(()))
+    }
+
+    fn poll_close(self: Pin<&mut Self>, _cx: &mut Context<'_>) -> Poll<std::io::Result<()>> {
+        self.get_mut().tx.close_channel();
+        Poll::Ready(Ok(()))
+    }
+}
@@ -5,31 +5,30 @@ import random
 
 """
 Open file `op.json`, and feed it to a Strompy writer in small chunks. Returns
-when all bytes have been fed.
+when all bytes have been fed. The writer is closed on exit, signalling the end
+of the input.
 """
 async def feed(writer):
-    async with aiofiles.open('op.json', mode='rb', buffering=1000) as file:
+    async with writer, aiofiles.open('op.json', mode='rb', buffering=1000) as file:
         while True:
             chunk = await file.read(random.randint(0, 128))
             if len(chunk) == 0:
                 break
-            await strompy.feed_bytes(writer, chunk)
+            await writer.send(chunk)
         print('Done reading!')
 
 """
-Poll the Strompy reader for execution results, returning once
-Strompy yields `None`
+Iterate over the execution results yielded by the Strompy reader, returning
+once the reader is exhausted. The reader is closed on exit.
 """
 async def poll(reader):
-    while True:
-        res = await reader.next()
-        if res is None:
-            break
-        print(f'Result: {res}')
+    async with reader:
+        async for res in reader:
+            print(f'Result: {res}')
 
 async def main():
-    # Set up a channel
-    writer, reader = strompy.channel()
+    # Set up a channel, buffering at most 4 chunks
+    writer, reader = strompy.channel(capacity=4)
     # Spawn feed and poll_next tasks
     write = asyncio.create_task(feed(writer))
     read = asyncio.create_task(poll(reader))
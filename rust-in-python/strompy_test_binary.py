@@ -0,0 +1,30 @@
+import asyncio
+import cbor2
+import msgpack
+import strompy
+
+work = [{
+    'lhs': {'d': [1.0, 2.0, 3.0, 4.0, 5.0, 6.0], 'n': 3},
+    'op': [{'code': 'transpose'}, {'code': 'scale', 'factor': 0.5}],
+}]
+
+# The same work, encoded as CBOR and as MessagePack
+print(strompy.exec(cbor2.dumps(work), format='cbor'))
+print(strompy.exec(msgpack.packb(work), format='msgpack'))
+
+async def main():
+    writer, reader = strompy.channel(format='msgpack')
+
+    async def feed():
+        data = msgpack.packb(work)
+        for i in range(0, len(data), 8):
+            await strompy.feed_bytes(writer, data[i:i + 8])
+
+    async def poll():
+        async with reader:
+            async for res in reader:
+                print(f'Result: {res}')
+
+    await asyncio.gather(feed(), poll())
+
+asyncio.run(main())
@@ -0,0 +1,38 @@
+import asyncio
+import json
+
+import strompy
+
+work = [{'lhs': {'d': [1, 2, 3, 4], 'n': 2}, 'op': [{'code': 'transpose'}]}]
+
+
+async def main():
+    writer, reader = strompy.channel()
+
+    # Send only part of the input, so that reading waits for the rest
+    data = json.dumps(work).encode()
+    await writer.send(data[:len(data) // 2])
+    read = asyncio.create_task(reader.next())
+    await asyncio.sleep(0.1)
+    assert not read.done()
+
+    # Cancelling from another task makes the pending read fail
+    await reader.cancel()
+    try:
+        await read
+    except strompy.StrompyError as e:
+        print(f'Pending read failed: {e}')
+    else:
+        raise AssertionError('Pending read did not fail')
+
+    # Reading after cancelling fails as well
+    try:
+        await reader.next()
+    except strompy.StrompyError as e:
+        print(f'Next read failed: {e}')
+    else:
+        raise AssertionError('Read after cancelling did not fail')
+
+    await writer.close()
+
+asyncio.run(main())
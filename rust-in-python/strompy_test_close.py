@@ -0,0 +1,43 @@
+import asyncio
+import json
+
+import strompy
+
+work = [
+    {'lhs': {'d': [1, 2, 3, 4], 'n': 2}, 'op': [{'code': 'transpose'}]},
+    {'lhs': {'d': [5, 6], 'n': 1}, 'op': [{'code': 'scale', 'factor': 2}]},
+]
+
+
+async def main():
+    writer, reader = strompy.channel()
+    await writer.send(json.dumps(work).encode())
+
+    # Results can be read up until the reader is closed...
+    print(f'Result: {await reader.next()}')
+    await reader.close()
+
+    # ...after which reading fails, even though there is more work
+    try:
+        await reader.next()
+    except strompy.StrompyError as e:
+        print(f'Read after closing failed: {e}')
+    else:
+        raise AssertionError('Read after closing did not fail')
+
+    # Closing from another task also makes a pending read fail
+    writer, reader = strompy.channel()
+    read = asyncio.create_task(reader.next())
+    await asyncio.sleep(0.1)
+    assert not read.done()
+    await reader.close()
+    try:
+        await read
+    except strompy.StrompyError as e:
+        print(f'Pending read failed: {e}')
+    else:
+        raise AssertionError('Pending read did not fail')
+
+    await writer.close()
+
+asyncio.run(main())
@@ -0,0 +1,26 @@
+import asyncio
+import json
+
+import strompy
+
+work = [
+    {'lhs': {'d': [1, 2, 3, 4], 'n': 2}, 'op': [{'code': 'transpose'}]},
+    {'lhs': {'d': [1, 2, 3], 'n': 2}, 'op': []},
+    {'lhs': {'d': [1, 2], 'n': 2}, 'op': [{'code': 'frobnicate'}]},
+    {'lhs': {'d': [5, 6], 'n': 1}, 'op': [{'code': 'scale', 'factor': 2}]},
+]
+
+async def main():
+    # Errors in a single piece of work are returned in place of its result
+    writer, reader = strompy.channel(on_error='continue')
+    async with writer:
+        await writer.send(json.dumps(work).encode())
+
+    async with reader:
+        async for res in reader:
+            if isinstance(res, strompy.ItemError):
+                print(f'Item {res.index} failed at {res.error.path}: {res.error}')
+            else:
+                print(f'Result: {res}')
+
+asyncio.run(main())
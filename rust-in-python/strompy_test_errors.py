@@ -0,0 +1,71 @@
+import asyncio
+import json
+
+import strompy
+
+missing = [{'lhs': {'d': [1, 2]}, 'op': []}]
+conflicting = [{'lhs': {'ref': 'x', 'd': [1], 'n': 1}, 'op': []}]
+invalid_shape = [{'lhs': {'d': [1, 2, 3], 'n': 2}, 'op': []}]
+wrong_n = [{'lhs': {'d': [1, 2], 'n': 'x'}, 'op': []}]
+wrong_d = [{'lhs': {'d': 'x', 'n': 1}, 'op': []}]
+
+
+def check_missing(e):
+    assert isinstance(e, strompy.UnexpectedKeyError) and isinstance(e, strompy.StrompyError)
+    assert (e.expected, e.found, e.path) == ('n', None, '$[0].lhs'), e
+
+
+def check_conflicting(e):
+    assert isinstance(e, strompy.UnexpectedKeyError)
+    assert (e.expected, e.found, e.path) == (None, 'd', '$[0].lhs'), e
+
+
+def check_invalid_shape(e):
+    assert isinstance(e, strompy.DimensionError)
+    assert (e.length, e.n, e.path) == (3, 2, '$[0].lhs'), e
+
+
+def check_wrong_type(e):
+    assert isinstance(e, strompy.JsonSyntaxError), e
+
+
+cases = [
+    (missing, check_missing),
+    (conflicting, check_conflicting),
+    (invalid_shape, check_invalid_shape),
+    (wrong_n, check_wrong_type),
+    (wrong_d, check_wrong_type),
+]
+
+# Work that is executed at once raises the same exceptions...
+exec_classes = []
+for work, check in cases:
+    try:
+        strompy.exec(json.dumps(work).encode())
+    except strompy.StrompyError as e:
+        print(f'exec: {type(e).__name__}: {e}')
+        check(e)
+        exec_classes.append(type(e))
+    else:
+        raise AssertionError(f'{work} did not fail')
+
+
+# ...as work that is read from a stream
+async def main():
+    reader_classes = []
+    for work, check in cases:
+        writer, reader = strompy.channel()
+        async with writer:
+            await writer.send(json.dumps(work).encode())
+        async with reader:
+            try:
+                await reader.next()
+            except strompy.StrompyError as e:
+                print(f'reader: {type(e).__name__}: {e}')
+                check(e)
+                reader_classes.append(type(e))
+            else:
+                raise AssertionError(f'{work} did not fail')
+    assert reader_classes == exec_classes, (reader_classes, exec_classes)
+
+asyncio.run(main())
@@ -0,0 +1,25 @@
+import asyncio
+import strompy
+
+"""
+Feed the contents of `op.json` to a Strompy writer in one go
+"""
+async def feed(writer):
+    with open('op.json', 'rb') as file:
+        await strompy.feed_bytes(writer, file.read())
+
+"""
+Write the JSON output of Strompy to `out.json`, chunk by chunk
+"""
+async def sink(receiver):
+    with open('out.json', 'wb') as file:
+        async for chunk in receiver:
+            file.write(chunk)
+
+async def main():
+    writer, reader = strompy.channel()
+    json_writer, receiver = strompy.json_channel()
+
+    await asyncio.gather(feed(writer), json_writer.write_all(reader), sink(receiver))
+
+asyncio.run(main())
@@ -0,0 +1,33 @@
+import asyncio
+import json
+
+import strompy
+
+"""
+Send work that becomes malformed partway through
+"""
+async def feed(writer):
+    async with writer:
+        await writer.send(b'[{"lhs": {"d": [1, 2], "n": 2}, "op": []}, ')
+        await writer.send(b'{"lhs": }]')
+
+"""
+Collect the JSON output of Strompy, which ends even though writing failed
+"""
+async def collect(receiver):
+    return b''.join([chunk async for chunk in receiver])
+
+async def main():
+    writer, reader = strompy.channel()
+    json_writer, receiver = strompy.json_channel()
+
+    _, written, out = await asyncio.gather(
+        feed(writer), json_writer.write_all(reader), collect(receiver), return_exceptions=True
+    )
+
+    # The error is raised, and the output holds the results up until it
+    assert isinstance(written, strompy.JsonSyntaxError), written
+    print(f'Writing failed: {written}')
+    assert json.loads(out) == [{'d': [1.0, 2.0], 'n': 2, 'order': 'col'}]
+
+asyncio.run(main())
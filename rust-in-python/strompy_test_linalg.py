@@ -0,0 +1,23 @@
+import json
+
+import numpy as np
+import strompy
+
+a = np.array([[4.0, 7.0], [2.0, 6.0]])
+
+# Decompositions result in a dict of their factors
+print(strompy.Work(a).inverse().exec(numpy=True))
+print(strompy.Work(a).solve(np.array([[1.0], [2.0]])).exec(numpy=True))
+print(strompy.Work(a).svd().exec(numpy=True))
+
+# Factors named with 'as' can be referred to one by one
+work = [
+    {'lhs': {'d': [1, 2, 3, 4, 5, 6], 'n': 3}, 'op': [{'code': 'qr'}], 'as': 'f'},
+    {'lhs': {'ref': 'f.q'}, 'op': [{'code': 'transpose'}, {'code': 'matmul', 'rhs': {'ref': 'f.q'}}]},
+]
+print(strompy.exec(json.dumps(work).encode()))
+
+try:
+    strompy.Work(np.array([[1.0, 2.0], [2.0, 4.0]])).inverse().exec()
+except strompy.LinAlgError as e:
+    print(f'{type(e).__name__}: {e}')
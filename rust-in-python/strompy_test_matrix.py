@@ -0,0 +1,41 @@
+import asyncio
+import json
+import pickle
+
+import numpy as np
+import strompy
+
+a = np.arange(1, 7, dtype=np.float64).reshape(2, 3)
+
+# Results can be returned as Matrix objects, which keep their shape
+m = strompy.Work(a).exec(matrix=True)
+print(repr(m))
+assert m.shape == (2, 3) and len(m) == 2
+assert m[1] == [4.0, 5.0, 6.0] and m[0, 2] == 3.0 and m[-1, -1] == 6.0
+assert list(m) == a.tolist()
+assert m == strompy.Matrix([1, 2, 3, 4, 5, 6], 3, order='row')
+assert pickle.loads(pickle.dumps(m)) == m
+
+# NumPy can view the elements without copying them
+assert (np.asarray(m) == a).all()
+assert np.asarray(m).flags.f_contiguous
+
+# Matrices can be passed on to new work as is
+res = strompy.Work(m).matmul(strompy.Work(a).transpose().exec(matrix=True)).exec(matrix=True)
+assert res == strompy.Work(a @ a.T).exec(matrix=True)
+
+work = [{'lhs': {'d': [1, 2, 3, 4], 'n': 2}, 'op': [{'code': 'qr'}]}]
+factors = strompy.exec(json.dumps(work).encode(), matrix=True)[0]
+print(strompy.Work(factors['q']).matmul(factors['r']).exec())
+
+
+async def main():
+    # The reader returns matrices as well, when asked to
+    writer, reader = strompy.channel()
+    async with writer:
+        await writer.send(json.dumps(work).encode())
+    async with reader:
+        factors = await reader.next(matrix=True)
+    print(factors)
+
+asyncio.run(main())
@@ -0,0 +1,19 @@
+import numpy as np
+import strompy
+
+a = np.arange(1, 10, dtype=np.float64).reshape(3, 3)
+b = np.eye(3)
+
+# Build a piece of work from NumPy arrays, without going through JSON
+res = strompy.Work(a).matmul(b).scale(0.5).exec(numpy=True)
+print(res)
+assert (res == a * 0.5).all()
+
+# Arrays keep their orientation, whatever their memory layout
+c = np.arange(1, 7, dtype=np.float64).reshape(2, 3)
+assert (strompy.Work(c).matmul(c.T).exec(numpy=True) == c @ c.T).all()
+assert (strompy.Work(np.asfortranarray(c)).transpose().exec(numpy=True) == c.T).all()
+
+# Results from JSON input can be returned as NumPy arrays as well
+with open('op.json', 'rb') as file:
+    print(strompy.exec(file.read(), numpy=True))
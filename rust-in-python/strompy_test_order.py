@@ -0,0 +1,24 @@
+import json
+
+import numpy as np
+import strompy
+
+a = np.arange(1, 7, dtype=np.float64).reshape(2, 3)
+
+# Matrix data can list the elements row by row or column by column
+work = [
+    {'lhs': {'d': a.flatten().tolist(), 'n': 3, 'order': 'row'}, 'op': []},
+    {'lhs': {'d': a.flatten(order='F').tolist(), 'n': 3, 'order': 'col'}, 'op': []},
+]
+for res in strompy.exec(json.dumps(work).encode()):
+    assert res == a.tolist(), res
+for res in strompy.exec(json.dumps(work).encode(), numpy=True):
+    assert (res == a).all(), res
+
+# Non-square arrays keep their shape, whatever their memory layout
+for array in [a, np.asfortranarray(a), a.T]:
+    res = strompy.Work(array).exec(numpy=True)
+    assert res.shape == array.shape and (res == array).all(), res
+    assert strompy.Work(array).exec() == array.tolist()
+
+print(strompy.Work(a).transpose().exec())
@@ -0,0 +1,18 @@
+import json
+
+import numpy as np
+import strompy
+
+a = np.array([[1.0, 2.0, -3.0], [-4.0, 5.0, 6.0]])
+
+# Element-wise functions keep the shape, reductions combine
+# all elements, or those of each row or column
+print(strompy.Work(a).abs().sum(axis='rows').exec(numpy=True))
+print(strompy.Work(a).clamp(min=0).mean(axis='cols').exec(numpy=True))
+print(strompy.Work(a).norm(kind='inf').exec(numpy=True))
+
+work = [
+    {'lhs': {'d': [1, -4, 2, 5, -3, 6], 'n': 3}, 'op': [{'code': 'pow', 'exponent': 2}, {'code': 'max', 'axis': 'cols'}]},
+    {'expr': {'code': 'norm', 'kind': 'l1', 'lhs': {'d': [1, -4, 2, 5, -3, 6], 'n': 3}}},
+]
+print(strompy.exec(json.dumps(work).encode()))
@@ -0,0 +1,18 @@
+import json
+
+import numpy as np
+import strompy
+
+a = np.arange(1, 13, dtype=np.float64).reshape(4, 3)
+
+# Python slices select blocks of rows and columns
+print(strompy.Work(a).slice(rows=slice(0, 4, 2), cols=slice(1, None)).exec(numpy=True))
+print(strompy.Work(a).hstack(np.zeros((4, 1))).diag().exec(numpy=True))
+
+# In JSON, ranges are objects, and concatenations can refer to earlier results
+work = [
+    {'lhs': {'d': [1, 2], 'n': 1}, 'op': [], 'as': 'a'},
+    {'lhs': {'ref': 'a'}, 'op': [{'code': 'vstack', 'rhs': {'ref': 'a'}}, {'code': 'reshape', 'shape': [2, 2]}]},
+    {'lhs': {'ref': 'a'}, 'op': [{'code': 'slice', 'rows': {'start': 1}}]},
+]
+print(strompy.exec(json.dumps(work).encode()))
@@ -0,0 +1,34 @@
+import json
+import time
+from concurrent.futures import ThreadPoolExecutor
+
+import strompy
+
+piece = {
+    'lhs': {'d': [float(x) for x in range(36)], 'n': 6},
+    'op': [{'code': 'matmul', 'rhs': {'d': [1.0 if i % 7 == 0 else 0.0 for i in range(36)], 'n': 6}}] * 4,
+}
+work = json.dumps([piece] * 20_000).encode()
+
+# Single call, spread over a fixed number of threads
+for threads in [1, 4]:
+    start = time.perf_counter()
+    strompy.exec(work, threads=threads)
+    print(f'{threads} thread(s): {time.perf_counter() - start:.3f}s')
+
+# The GIL is released while executing, so calls from Python threads run concurrently
+start = time.perf_counter()
+with ThreadPoolExecutor(max_workers=4) as executor:
+    list(executor.map(lambda _: strompy.exec(work, threads=1), range(4)))
+print(f'4 Python threads: {time.perf_counter() - start:.3f}s')
+
+# Calls with ever different numbers of threads do not keep their pools around
+for threads in range(1, 33):
+    strompy.exec(json.dumps([piece]).encode(), threads=threads)
+
+try:
+    strompy.exec(work, threads=0)
+except ValueError as e:
+    print(f'0 threads: {e}')
+else:
+    raise AssertionError('0 threads did not fail')
@@ -0,0 +1,22 @@
+import asyncio
+import json
+
+import strompy
+
+work = [
+    {'lhs': {'d': [1, 2, 3, 4], 'n': 2}, 'op': [{'code': 'transpose'}], 'as': 'a'},
+    {'lhs': {'ref': 'a'}, 'op': [{'code': 'matmul', 'rhs': {'d': [1, 2, 3], 'n': 3}}]},
+    {'lhs': {'ref': 'b'}, 'op': [{'code': 'frobnicate'}]},
+]
+
+async def main():
+    # Every problem is reported, without executing any of the work
+    for problem in strompy.validate(json.dumps(work).encode()):
+        print(f'{type(problem).__name__} at {problem.path}: {problem}')
+
+    writer, reader = strompy.channel()
+    async with writer:
+        await writer.send(json.dumps(work).encode())
+    print(await reader.validate())
+
+asyncio.run(main())
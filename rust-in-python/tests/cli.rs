@@ -0,0 +1,56 @@
+//! Runs the `strompy` binary, which is only built with the `cli` feature
+#![cfg(feature = "cli")]
+
+use std::{
+    io::Write,
+    process::{Command, Output, Stdio},
+};
+
+use serde_json::Value;
+
+/// Run `strompy` with `args`, feeding it `input` on standard input
+fn strompy(args: &[&str], input: &str) -> Output {
+    let mut child = Command::new(env!("CARGO_BIN_EXE_strompy"))
+        .args(args)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .unwrap();
+    child
+        .stdin
+        .take()
+        .unwrap()
+        .write_all(input.as_bytes())
+        .unwrap();
+    child.wait_with_output().unwrap()
+}
+
+const WORK: &str = r#"[
+    { "lhs": { "d": [1, 2, 3, 4], "n": 2 }, "op": [{ "code": "transpose" }] },
+    { "lhs": { "d": [1, 2, 3], "n": 2 }, "op": [] },
+    { "lhs": { "d": [5, 6], "n": 1 }, "op": [{ "code": "scale", "factor": 2 }] }
+]"#;
+
+#[test]
+fn it_stops_at_the_first_error() {
+    let output = strompy(&[], WORK);
+    assert!(!output.status.success());
+    assert!(!output.stderr.is_empty());
+
+    // The output is still a complete array, holding the results before the error
+    let results: Vec<Value> = serde_json::from_slice(&output.stdout).unwrap();
+    assert_eq!(results.len(), 1);
+    assert_eq!(results[0]["d"], serde_json::json!([1.0, 3.0, 2.0, 4.0]));
+}
+
+#[test]
+fn it_continues_after_errors() {
+    let output = strompy(&["--continue-on-error", "--jobs", "1"], WORK);
+    assert!(!output.status.success());
+
+    let results: Vec<Value> = serde_json::from_slice(&output.stdout).unwrap();
+    assert_eq!(results.len(), 3);
+    assert!(results[1].get("error").is_some());
+    assert_eq!(results[2]["d"], serde_json::json!([10.0, 12.0]));
+}